@@ -0,0 +1,240 @@
+use crate::types::PathOverrides;
+use std::path::PathBuf;
+
+const USAGE: &str = r#"用法: rime_wanxiang_updater [选项] [命令]
+
+命令:
+  (无)                     检查并应用更新
+  rollback [组件] [快照]   恢复更新前的快照，组件为 schema、dict 或 model，
+                           省略时恢复最新的快照
+  mirrors probe            对镜像站测速并保存结果，供 mirrors = "auto" 使用
+
+选项:
+  --config <路径>          指定配置文件
+  --portable               便携模式：配置、缓存、状态和日志均放在程序旁边
+  --user-dir <路径>        指定 Rime 用户目录
+  --frontend-root <路径>   指定输入法前端安装目录
+  --curl <路径>            指定 curl 可执行文件
+  --local-root <目录>      从本地目录读取预置的 Release 数据和资产文件（离线测试）
+  --local-server <目录>    在 localhost 上启动 HTTP 服务提供本地目录，并通过它访问
+  --pin <组件>=<值>        固定组件版本并保存到配置文件，可重复使用，值为空时取消固定
+                           schema=<版本标签>、dict=<资产 ID 或 YYYY-MM-DD>、model=<标签>
+  -h, --help               显示帮助信息"#;
+
+/// 可以用 --pin 固定版本的组件
+const PIN_COMPONENTS: [&str; 3] = ["schema", "dict", "model"];
+
+/// 要执行的命令
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Command {
+    /// 检查并应用更新
+    #[default]
+    Update,
+    /// 恢复快照
+    Rollback {
+        component: Option<String>,
+        snapshot: Option<String>,
+    },
+    /// 对镜像站测速
+    ProbeMirrors,
+}
+
+/// 命令行参数
+#[derive(Debug, Clone, Default)]
+pub struct CliArgs {
+    pub command: Command,
+    pub paths: PathOverrides,
+    pub config: Option<PathBuf>,
+    pub portable: bool,
+    pub local_root: Option<PathBuf>,
+    pub local_server: Option<PathBuf>,
+    /// --pin 指定的 (组件, 固定值)
+    pub pins: Vec<(String, String)>,
+}
+
+impl CliArgs {
+    /// 解析当前进程的命令行参数，参数有误时打印用法并退出
+    pub fn from_env() -> Self {
+        match Self::parse(std::env::args().skip(1)) {
+            Ok(Some(args)) => args,
+            Ok(None) => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("❌ {}\n\n{}", e, USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    /// 解析命令行参数，遇到 --help 时返回 None
+    ///
+    /// 同时支持 `--flag value` 和 `--flag=value` 两种写法
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Option<Self>, String> {
+        let mut result = CliArgs::default();
+        let mut positional = Vec::new();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+
+            let mut value = |name: &str| -> Result<String, String> {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .filter(|v| !v.is_empty())
+                    .ok_or_else(|| format!("参数 {} 缺少值", name))
+            };
+            let mut path = |name: &str| value(name).map(PathBuf::from);
+
+            match flag.as_str() {
+                "--config" => result.config = Some(path(&flag)?),
+                "--portable" => result.portable = true,
+                "--local-root" => result.local_root = Some(path(&flag)?),
+                "--local-server" => result.local_server = Some(path(&flag)?),
+                "--user-dir" => result.paths.user_dir = Some(path(&flag)?),
+                "--frontend-root" => result.paths.frontend_root = Some(path(&flag)?),
+                "--curl" => result.paths.curl = Some(path(&flag)?),
+                "--7z" => {
+                    value(&flag)?;
+                    eprintln!("⚠️ --7z 参数已废弃，压缩包改为内置解压，该参数将被忽略");
+                }
+                "--pin" => result.pins.push(Self::parse_pin(&value(&flag)?)?),
+                "-h" | "--help" => return Ok(None),
+                _ if !arg.starts_with('-') => positional.push(arg),
+                _ => return Err(format!("未知参数: {}", arg)),
+            }
+        }
+
+        result.command = Self::parse_command(positional)?;
+        Ok(Some(result))
+    }
+
+    /// 解析 `组件=值`
+    fn parse_pin(pin: &str) -> Result<(String, String), String> {
+        match pin.split_once('=') {
+            Some((component, value)) if PIN_COMPONENTS.contains(&component.trim()) => {
+                Ok((component.trim().to_string(), value.trim().to_string()))
+            }
+            Some((component, _)) => Err(format!("无法固定的组件: {}", component)),
+            None => Err(format!("--pin 的格式应为 <组件>=<值>: {}", pin)),
+        }
+    }
+
+    fn parse_command(positional: Vec<String>) -> Result<Command, String> {
+        let mut positional = positional.into_iter();
+        match positional.next().as_deref() {
+            None => Ok(Command::Update),
+            Some("rollback") => {
+                let component = positional.next();
+                let snapshot = positional.next();
+                if let Some(extra) = positional.next() {
+                    return Err(format!("多余的参数: {}", extra));
+                }
+                Ok(Command::Rollback {
+                    component,
+                    snapshot,
+                })
+            }
+            Some("mirrors") => match (positional.next().as_deref(), positional.next()) {
+                (Some("probe"), None) => Ok(Command::ProbeMirrors),
+                (Some("probe"), Some(extra)) => Err(format!("多余的参数: {}", extra)),
+                (Some(other), _) => Err(format!("未知命令: mirrors {}", other)),
+                (None, _) => Err("缺少子命令: mirrors probe".to_string()),
+            },
+            Some(other) => Err(format!("未知命令: {}", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<CliArgs>, String> {
+        CliArgs::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_parse_path_flags() {
+        let args = parse(&[
+            "--user-dir",
+            "/tmp/rime",
+            "--frontend-root=/opt/rime",
+            "--curl",
+            "curl",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(args.paths.user_dir, Some(PathBuf::from("/tmp/rime")));
+        assert_eq!(args.paths.frontend_root, Some(PathBuf::from("/opt/rime")));
+        assert_eq!(args.paths.curl, Some(PathBuf::from("curl")));
+    }
+
+    #[test]
+    fn test_deprecated_7z_flag() {
+        let args = parse(&["--7z", "/opt/7zz", "--curl=curl"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(args.paths.curl, Some(PathBuf::from("curl")));
+        assert!(parse(&["--7z=/opt/7zz"]).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(&["--user-dir"]).is_err());
+        assert!(parse(&["--unknown"]).is_err());
+        assert!(parse(&["--help"]).unwrap().is_none());
+        assert!(parse(&["--config"]).is_err());
+        assert!(parse(&["frobnicate"]).is_err());
+        assert!(parse(&["rollback", "dict", "1", "2"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_pin() {
+        let args = parse(&[
+            "--pin",
+            "schema=v10.0.0",
+            "--pin=dict=2025-06-01",
+            "--pin",
+            "model=",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            args.pins,
+            vec![
+                ("schema".to_string(), "v10.0.0".to_string()),
+                ("dict".to_string(), "2025-06-01".to_string()),
+                ("model".to_string(), String::new()),
+            ]
+        );
+        assert!(parse(&["--pin", "self=v1"]).is_err());
+        assert!(parse(&["--pin", "schema"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_rollback() {
+        let args = parse(&["--portable", "rollback", "dict"]).unwrap().unwrap();
+        assert!(args.portable);
+        assert_eq!(
+            args.command,
+            Command::Rollback {
+                component: Some("dict".to_string()),
+                snapshot: None,
+            }
+        );
+        assert_eq!(parse(&[]).unwrap().unwrap().command, Command::Update);
+        assert_eq!(
+            parse(&["mirrors", "probe"]).unwrap().unwrap().command,
+            Command::ProbeMirrors
+        );
+        assert!(parse(&["mirrors"]).is_err());
+        assert!(parse(&["mirrors", "list"]).is_err());
+    }
+}
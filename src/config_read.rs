@@ -1,14 +1,18 @@
-use crate::types::UpdateConfig;
+use crate::types::{
+    Channel, ConflictPolicy, FilterConfig, PathOverrides, RemovedFilePolicy, UpdateConfig,
+};
+use crate::update_checker::{frontend::FrontendKind, mirror};
 use ini::Ini;
 use std::{
     io::{self, Write},
     path::PathBuf,
 };
 
-/// 初始化配置时的交互式方案选择
-pub fn init_config_with_selection(config_path: &PathBuf) -> UpdateConfig {
-    let mut config = UpdateConfig::default();
+/// 支持 [filters] 筛选的组件，模型只有单个文件，不参与筛选
+const FILTER_COMPONENTS: [&str; 2] = ["schema", "dict"];
 
+/// 初始化配置时的交互式方案选择，在 config 的基础上修改后写入配置文件
+pub fn init_config_with_selection(config_path: &PathBuf, mut config: UpdateConfig) -> UpdateConfig {
     println!("\n=== 万象输入法方案配置向导 ===");
 
     // 选择方案版本
@@ -25,6 +29,16 @@ pub fn init_config_with_selection(config_path: &PathBuf) -> UpdateConfig {
         config.dict_name = "9-base-dicts.zip".to_string();
     }
 
+    // 选择发布渠道，方案和程序自身使用同一渠道
+    let channel = select_channel();
+    config.channels.schema = channel;
+    config.channels.self_update = channel;
+
+    // 是否自动测速选择镜像
+    if select_auto_mirror() {
+        config.mirrors = vec![mirror::AUTO.to_string()];
+    }
+
     // 显示选择结果
     println!("\n✅ 配置完成：");
     println!(
@@ -40,6 +54,10 @@ pub fn init_config_with_selection(config_path: &PathBuf) -> UpdateConfig {
     }
     println!("  方案文件: {}", config.schema_name);
     println!("  词库文件: {}", config.dict_name);
+    println!("  发布渠道: {}", get_channel_display_name(channel));
+    if mirror::is_auto(&config.mirrors) {
+        println!("  下载镜像: 自动选择（首次下载前测速）");
+    }
 
     // 写入配置文件
     write_default_config(config_path, &config);
@@ -129,21 +147,287 @@ fn get_schema_display_name(key: &str) -> &'static str {
     }
 }
 
+/// 选择发布渠道
+fn select_channel() -> Channel {
+    loop {
+        println!("\n请选择更新渠道：");
+        println!("[1] 稳定版 (stable) - 只使用正式版本，推荐");
+        println!("[2] 测试版 (beta) - 同时接受预发布版本");
+        println!("[3] 每日构建 (nightly) - 跟随滚动更新的 nightly 标签");
+
+        print!("请输入选择 (1-3): ");
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input).is_ok() {
+            let choice = match input.trim() {
+                "1" => Some(Channel::Stable),
+                "2" => Some(Channel::Beta),
+                "3" => Some(Channel::Nightly),
+                _ => None,
+            };
+
+            if let Some(channel) = choice {
+                println!("✅ 已选择：{}", get_channel_display_name(channel));
+                return channel;
+            } else {
+                println!("❌ 输入无效，请重新选择");
+            }
+        }
+    }
+}
+
+/// 询问是否自动测速选择镜像
+fn select_auto_mirror() -> bool {
+    loop {
+        println!("\n是否对镜像站测速并自动选择最快的镜像？");
+        println!("[1] 是 - 首次下载前测速，之后定期重新测速");
+        println!("[2] 否 - 直接从 GitHub 下载，之后可在配置文件中填写镜像");
+
+        print!("请输入选择 (1-2): ");
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input).is_ok() {
+            match input.trim() {
+                "1" => return true,
+                "2" => return false,
+                _ => println!("❌ 输入无效，请重新选择"),
+            }
+        }
+    }
+}
+
+/// 获取发布渠道显示名称
+fn get_channel_display_name(channel: Channel) -> &'static str {
+    match channel {
+        Channel::Stable => "稳定版",
+        Channel::Beta => "测试版",
+        Channel::Nightly => "每日构建",
+    }
+}
+
+/// 检测到多个前端时的交互式选择
+pub fn select_frontend(candidates: &[FrontendKind]) -> FrontendKind {
+    loop {
+        println!("\n检测到多个已安装的输入法前端，请选择要更新的前端：");
+        for (i, kind) in candidates.iter().enumerate() {
+            println!("[{}] {}", i + 1, kind.display_name());
+        }
+
+        print!("请输入选择 (1-{}): ", candidates.len());
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input).is_ok() {
+            match input.trim().parse::<usize>() {
+                Ok(n) if n >= 1 && n <= candidates.len() => {
+                    let kind = candidates[n - 1];
+                    println!("✅ 已选择：{}", kind.display_name());
+                    return kind;
+                }
+                _ => println!("❌ 输入无效，请重新选择"),
+            }
+        }
+    }
+}
+
+/// 修改配置文件中的单个键值，保留其余内容和注释
+///
+/// 键不存在时追加到对应节的末尾，节不存在时追加新节
+pub fn set_config_value(
+    config_path: &PathBuf,
+    section: &str,
+    key: &str,
+    value: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let content = std::fs::read_to_string(config_path).unwrap_or_default();
+    let new_line = format!("{} = \"{}\"", key, value);
+    let header = format!("[{}]", section);
+
+    let mut lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
+    let mut in_section = false;
+    let mut section_end = None;
+    let mut replaced = false;
+
+    for (i, line) in lines.iter_mut().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            if in_section {
+                section_end = Some(i);
+                break;
+            }
+            in_section = trimmed == header;
+            continue;
+        }
+        if in_section {
+            if let Some((k, _)) = trimmed.split_once('=') {
+                if !trimmed.starts_with('#') && k.trim() == key {
+                    *line = new_line.clone();
+                    replaced = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    if !replaced {
+        if in_section {
+            // 插入到节末尾的空行之前
+            let mut pos = section_end.unwrap_or(lines.len());
+            while pos > 0 && lines[pos - 1].trim().is_empty() {
+                pos -= 1;
+            }
+            lines.insert(pos, new_line);
+        } else {
+            if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push(header);
+            lines.push(new_line);
+        }
+    }
+
+    std::fs::write(config_path, lines.join("\n") + "\n")?;
+    Ok(())
+}
+
+/// 修改组件的固定版本并写入配置文件，value 为空时取消固定
+///
+/// 词库的值为纯数字时视为资产 ID，否则视为 `YYYY-MM-DD` 格式的更新日期
+pub fn set_pin(
+    config_path: &PathBuf,
+    config: &mut UpdateConfig,
+    component: &str,
+    value: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let value = value.trim();
+    let pins = &mut config.pins;
+    let mut values: Vec<(&str, String)> = Vec::new();
+    match component {
+        "schema" => {
+            pins.schema_version = value.to_string();
+            values.push(("schema_version", value.to_string()));
+        }
+        "model" => {
+            pins.model_version = value.to_string();
+            values.push(("model_version", value.to_string()));
+        }
+        "dict" => {
+            pins.dict_asset_id = None;
+            pins.dict_date.clear();
+            if let Ok(id) = value.parse::<u64>() {
+                pins.dict_asset_id = Some(id);
+            } else if is_date(value) {
+                pins.dict_date = value.to_string();
+            } else if !value.is_empty() {
+                return Err(
+                    format!("词库的固定值应为资产 ID 或 YYYY-MM-DD 日期: {}", value).into(),
+                );
+            }
+            values.push((
+                "dict_asset_id",
+                pins.dict_asset_id
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+            ));
+            values.push(("dict_date", pins.dict_date.clone()));
+        }
+        _ => return Err(format!("未知的组件: {}", component).into()),
+    }
+
+    for (key, value) in values {
+        set_config_value(config_path, "pins", key, &value)?;
+    }
+    Ok(())
+}
+
+/// 是否为 `YYYY-MM-DD` 格式的日期
+fn is_date(value: &str) -> bool {
+    let parts: Vec<&str> = value.split('-').collect();
+    parts.len() == 3
+        && [4, 2, 2]
+            .iter()
+            .zip(&parts)
+            .all(|(len, part)| part.len() == *len && part.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// 读取配置文件中 [paths] 节手动指定的路径
+pub fn read_path_overrides(config_path: &PathBuf) -> PathOverrides {
+    let mut overrides = PathOverrides::default();
+
+    if let Ok(ini) = Ini::load_from_file(config_path) {
+        if let Some(paths) = ini.section(Some("paths")) {
+            let get = |key: &str| {
+                paths
+                    .get(key)
+                    .map(|v| v.trim_matches('"'))
+                    .filter(|v| !v.is_empty())
+                    .map(PathBuf::from)
+            };
+            overrides.user_dir = get("user_dir");
+            overrides.frontend_root = get("frontend_root");
+            overrides.curl = get("curl");
+            if get("7z").is_some() {
+                eprintln!("⚠️ 配置项 [paths] 7z 已废弃，压缩包改为内置解压，该配置将被忽略");
+            }
+        }
+    }
+
+    overrides
+}
+
 /// 读取配置文件
-pub fn read_config(config_path: &PathBuf) -> UpdateConfig {
+///
+/// 配置文件不存在、不完整或刚由默认配置新建（created）时启动配置向导
+pub fn read_config(config_path: &PathBuf, created: bool) -> UpdateConfig {
     let mut config = UpdateConfig::default();
 
     if !config_path.exists() {
         println!("配置文件不存在，启动配置向导");
-        return init_config_with_selection(config_path);
+        return init_config_with_selection(config_path, UpdateConfig::default());
     }
 
     match Ini::load_from_file(config_path) {
         Ok(ini) => {
             // 读取 [general] 节
             if let Some(general) = ini.section(Some("general")) {
-                if let Some(mirror) = general.get("mirror") {
-                    config.mirror = mirror.trim_matches('"').to_string();
+                // 兼容旧版本的单个 mirror 配置
+                if let Some(mirrors) = general.get("mirrors").or_else(|| general.get("mirror")) {
+                    config.mirrors = split_list(mirrors)
+                        .into_iter()
+                        .filter(|m| match mirror::validate(m) {
+                            Ok(()) => true,
+                            Err(e) => {
+                                eprintln!("⚠️ 无效的镜像配置 {}: {}，已忽略", m, e);
+                                false
+                            }
+                        })
+                        .collect();
+                }
+                if let Some(retries) = general.get("download_retries") {
+                    config.download_retries = retries
+                        .trim_matches('"')
+                        .parse()
+                        .unwrap_or(config.download_retries);
+                }
+                if let Some(ttl) = general.get("mirror_probe_ttl") {
+                    config.mirror_probe_ttl = ttl
+                        .trim_matches('"')
+                        .parse()
+                        .unwrap_or(config.mirror_probe_ttl);
+                }
+                if let Some(frontend) = general.get("frontend") {
+                    config.frontend = frontend.trim_matches('"').to_string();
+                }
+                if let Some(transport) = general.get("transport") {
+                    config.transport = transport.trim_matches('"').to_string();
+                }
+                if let Some(keep) = general.get("snapshot_keep") {
+                    config.snapshot_keep = keep
+                        .trim_matches('"')
+                        .parse()
+                        .unwrap_or(config.snapshot_keep);
                 }
             }
 
@@ -196,23 +480,179 @@ pub fn read_config(config_path: &PathBuf) -> UpdateConfig {
                 }
             }
 
+            // 读取 [deploy] 节
+            if let Some(deploy) = ini.section(Some("deploy")) {
+                if let Some(command) = deploy.get("command") {
+                    config.deploy.command = command.trim_matches('"').to_string();
+                }
+                if let Some(args) = deploy.get("args") {
+                    config.deploy.args = split_args(args.trim_matches('"'));
+                }
+                if let Some(working_dir) = deploy.get("working_dir") {
+                    let working_dir = working_dir.trim_matches('"');
+                    config.deploy.working_dir = if working_dir.is_empty() {
+                        None
+                    } else {
+                        Some(PathBuf::from(working_dir))
+                    };
+                }
+                if let Some(timeout) = deploy.get("timeout") {
+                    config.deploy.timeout = timeout.trim_matches('"').parse().unwrap_or(0);
+                }
+                if let Some(code) = deploy.get("expected_exit_code") {
+                    config.deploy.expected_exit_code = code.trim_matches('"').parse().unwrap_or(0);
+                }
+            }
+
+            // 读取 [install] 节
+            if let Some(install) = ini.section(Some("install")) {
+                if let Some(policy) = install.get("removed_files") {
+                    let policy = policy.trim_matches('"');
+                    match RemovedFilePolicy::parse(policy) {
+                        Some(policy) => config.install.removed_files = policy,
+                        None => eprintln!("⚠️ 未知的 removed_files 设置: {}，使用默认值", policy),
+                    }
+                }
+                if let Some(policy) = install.get("conflicts") {
+                    let policy = policy.trim_matches('"');
+                    match ConflictPolicy::parse(policy) {
+                        Some(policy) => config.install.conflicts = policy,
+                        None => eprintln!("⚠️ 未知的 conflicts 设置: {}，使用默认值", policy),
+                    }
+                }
+            }
+
+            // 读取 [filters] 节
+            if let Some(filters) = ini.section(Some("filters")) {
+                for component in FILTER_COMPONENTS {
+                    let filter = FilterConfig {
+                        include: split_list(
+                            filters.get(format!("{}_include", component)).unwrap_or(""),
+                        ),
+                        exclude: split_list(
+                            filters.get(format!("{}_exclude", component)).unwrap_or(""),
+                        ),
+                    };
+                    if !filter.include.is_empty() || !filter.exclude.is_empty() {
+                        config.filters.insert(component.to_string(), filter);
+                    }
+                }
+            }
+
+            // 读取 [channels] 节
+            if let Some(channels) = ini.section(Some("channels")) {
+                let channel = |key: &str, default: Channel| {
+                    let Some(value) = channels.get(key) else {
+                        return default;
+                    };
+                    let value = value.trim_matches('"');
+                    Channel::parse(value).unwrap_or_else(|| {
+                        eprintln!("⚠️ 未知的渠道 {} = {}，使用 stable", key, value);
+                        Channel::Stable
+                    })
+                };
+                config.channels.schema = channel("schema", config.channels.schema);
+                config.channels.self_update = channel("self", config.channels.self_update);
+                if let Some(tag) = channels.get("schema_nightly_tag") {
+                    config.channels.schema_nightly_tag = tag.trim_matches('"').to_string();
+                }
+                if let Some(tag) = channels.get("self_nightly_tag") {
+                    config.channels.self_nightly_tag = tag.trim_matches('"').to_string();
+                }
+            }
+
+            // 读取 [pins] 节
+            if let Some(pins) = ini.section(Some("pins")) {
+                let get = |key: &str| pins.get(key).unwrap_or("").trim_matches('"').trim();
+                config.pins.schema_version = get("schema_version").to_string();
+                config.pins.dict_date = get("dict_date").to_string();
+                config.pins.model_version = get("model_version").to_string();
+                let asset_id = get("dict_asset_id");
+                if !asset_id.is_empty() {
+                    match asset_id.parse() {
+                        Ok(id) => config.pins.dict_asset_id = Some(id),
+                        Err(_) => eprintln!("⚠️ 无效的 dict_asset_id: {}，已忽略", asset_id),
+                    }
+                }
+            }
+
             // 检查是否需要重新配置方案
             if config.schema_type.is_empty() || config.schema_name.is_empty() {
                 println!("检测到配置不完整，启动方案选择向导");
-                return init_config_with_selection(config_path);
+                return init_config_with_selection(config_path, UpdateConfig::default());
+            }
+
+            // 首次运行时保留已检测到的前端等设置，其余通过向导选择
+            if created {
+                println!("首次运行，启动配置向导");
+                return init_config_with_selection(config_path, config);
             }
 
             println!("✅ 配置文件读取成功");
         }
         Err(e) => {
             eprintln!("读取配置文件失败: {}, 启动配置向导", e);
-            return init_config_with_selection(config_path);
+            return init_config_with_selection(config_path, UpdateConfig::default());
         }
     }
 
     config
 }
 
+/// 逗号分隔的列表，如路径模式和镜像
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .trim_matches('"')
+        .split(',')
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+/// 按空白拆分命令行参数，支持用单引号包裹含空格的参数
+fn split_args(value: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quote = false;
+    let mut has_arg = false;
+
+    for c in value.chars() {
+        match c {
+            '\'' => {
+                in_quote = !in_quote;
+                has_arg = true;
+            }
+            c if c.is_whitespace() && !in_quote => {
+                if has_arg {
+                    args.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_arg = true;
+            }
+        }
+    }
+    if has_arg {
+        args.push(current);
+    }
+    args
+}
+
+/// 配置文件中 [filters] 某一项的值
+fn filter_value(
+    config: &UpdateConfig,
+    component: &str,
+    list: fn(&FilterConfig) -> &[String],
+) -> String {
+    config
+        .filters
+        .get(component)
+        .map(|f| list(f).join(", "))
+        .unwrap_or_default()
+}
+
 fn write_default_config(config_path: &PathBuf, config: &UpdateConfig) {
     // 确保配置文件目录存在
     if let Some(parent) = config_path.parent() {
@@ -232,10 +672,45 @@ fn write_default_config(config_path: &PathBuf, config: &UpdateConfig) {
 
 [general]
 # 镜像网站配置 - 用于加速 GitHub 文件下载
+# 可填写多个，用逗号分隔，按顺序尝试，全部失败后直接使用 GitHub 原始链接
 # 如果留空则直接使用 GitHub 原始链接
+# 填写 auto 则对候选镜像测速，使用比直连更快的镜像，auto 后面的镜像也参与测速
+# 也可以运行 rime_wanxiang_updater mirrors probe 手动测速
+# 不是在原始链接前加前缀的镜像可以写成地址模板，如 https://kkgithub.com/{{path}}
+# 可用占位符: {{url}} 原始链接, {{path}} 去掉 https://github.com/ 后的路径,
+#            {{owner}} {{repo}} 仓库, {{tag}} {{file}} Release 标签和文件名
 # 可选镜像站示例：github.sagolu.top, gh-proxy.com, github.chenc.dev
 # 更多镜像站请参考：https://github.akams.cn/
-mirror = "{}"
+mirrors = "{}"
+
+# 每个下载源遇到网络错误或服务器繁忙时最多尝试的次数，重试间隔逐次翻倍
+download_retries = "{}"
+
+# 自动选择镜像时测速结果的有效时长（小时），过期后下次下载前重新测速
+mirror_probe_ttl = "{}"
+
+# 输入法前端 (weasel, fcitx5, ibus)
+# 留空则自动检测，检测到多个前端时会询问并保存选择
+frontend = "{}"
+
+# 网络传输方式
+# auto: 使用内置 HTTP 客户端，失败时回退到 curl
+# native: 仅使用内置 HTTP 客户端
+# curl: 使用 curl 程序（未找到时回退到内置客户端）
+transport = "{}"
+
+# 每个组件保留的更新前快照数量，用于 rollback 命令和部署失败时自动恢复
+# 设为 0 则不创建快照
+snapshot_keep = "{}"
+
+[paths]
+# 手动指定路径（可选），留空则自动查找，命令行参数优先于此处配置
+# user_dir: Rime 用户目录
+user_dir = ""
+# frontend_root: 输入法前端安装目录，Linux 下为前端程序所在目录（如 /usr/bin）
+frontend_root = ""
+# curl: 工具程序路径
+curl = ""
 
 [repositories]
 # GitHub 仓库配置 - 格式为 "用户名/仓库名"
@@ -281,8 +756,65 @@ model_file_name = "{}"
 # 格式示例: "session=xxx; _octo=xxx; logged_in=yes"
 # 获取方法：浏览器登录 GitHub 后，在开发者工具中查看 Cookie
 github_cookies = "{}"
+
+[deploy]
+# 自定义部署命令（可选）
+# 留空则使用前端自带的部署方式；设为 "none" 则只更新文件，不重新部署
+command = "{}"
+# 命令参数，以空格分隔，含空格的参数可用单引号包裹
+args = "{}"
+# 工作目录，留空则使用当前目录
+working_dir = "{}"
+# 超时时间（秒），0 表示不限制
+timeout = "{}"
+# 视为部署成功的退出码
+expected_exit_code = "{}"
+
+[install]
+# 上游已删除的文件（曾由本程序安装，新版本中不再包含）的处理方式
+# quarantine: 移动到状态目录的 quarantine 中
+# delete: 直接删除
+# keep: 保留不动
+# 不是由本程序安装的文件以及安装后被修改过的文件都不会被处理
+removed_files = "{}"
+# 安装后被修改过的文件（如 custom_phrase.txt）在新版本中也有时的处理方式
+# keep_user: 保留你的文件，新版本写为同名的 .new 文件
+# backup: 把你的文件备份到状态目录的 backups 中，再使用新版本
+conflicts = "{}"
+
+[filters]
+# 各组件只安装或不安装的文件，逗号分隔的 glob 模式，相对于解压目录
+# 不含 / 的模式匹配任意层级的文件或目录名，如 "*.lua"；含 / 的匹配完整路径，如 "lua/wanxiang*.lua"
+# 未设置 include 时安装全部文件，exclude 优先于 include
+schema_include = "{}"
+schema_exclude = "{}"
+dict_include = "{}"
+dict_exclude = "{}"
+
+[channels]
+# 发布渠道: stable 只使用正式版本，beta 同时接受预发布版本，nightly 跟随滚动更新的标签
+schema = "{}"
+schema_nightly_tag = "{}"
+self = "{}"
+self_nightly_tag = "{}"
+
+[pins]
+# 固定版本，留空表示跟随最新版本；固定到比已安装更旧的版本时会降级
+# 也可以使用 --pin schema=v10.0.0 等命令行参数修改
+# 方案版本标签，如 v10.0.0
+schema_version = "{}"
+# 词库资产 ID 或更新日期（如 2025-06-01），只有当前发布的资产符合时才会安装
+dict_asset_id = "{}"
+dict_date = "{}"
+# 模型的 Release 标签
+model_version = "{}"
 "#,
-        config.mirror,
+        config.mirrors.join(", "),
+        config.download_retries,
+        config.mirror_probe_ttl,
+        config.frontend,
+        config.transport,
+        config.snapshot_keep,
         config.schema_repo,
         config.dict_repo,
         config.model_repo,
@@ -294,7 +826,45 @@ github_cookies = "{}"
         config.dict_tag,
         config.model_tag,
         config.model_file_name,
-        config.github_cookies.as_deref().unwrap_or("")
+        config.github_cookies.as_deref().unwrap_or(""),
+        config.deploy.command,
+        config
+            .deploy
+            .args
+            .iter()
+            .map(|a| if a.contains(' ') {
+                format!("'{}'", a)
+            } else {
+                a.clone()
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        config
+            .deploy
+            .working_dir
+            .as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_default(),
+        config.deploy.timeout,
+        config.deploy.expected_exit_code,
+        config.install.removed_files.as_str(),
+        config.install.conflicts.as_str(),
+        filter_value(config, "schema", |f| &f.include),
+        filter_value(config, "schema", |f| &f.exclude),
+        filter_value(config, "dict", |f| &f.include),
+        filter_value(config, "dict", |f| &f.exclude),
+        config.channels.schema.as_str(),
+        config.channels.schema_nightly_tag,
+        config.channels.self_update.as_str(),
+        config.channels.self_nightly_tag,
+        config.pins.schema_version,
+        config
+            .pins
+            .dict_asset_id
+            .map(|id| id.to_string())
+            .unwrap_or_default(),
+        config.pins.dict_date,
+        config.pins.model_version
     );
 
     if let Err(e) = std::fs::write(config_path, ini_content) {
@@ -303,3 +873,85 @@ github_cookies = "{}"
         println!("✅ 已创建默认配置文件: {:?}", config_path);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_config_value() {
+        let config_path = std::env::temp_dir().join("test_set_config_value.ini");
+        std::fs::write(
+            &config_path,
+            "[general]\n# 镜像\nmirror = \"gh-proxy.com\"\n\n[files]\nschema_type = \"base\"\n",
+        )
+        .unwrap();
+
+        // 新增键插入到对应节中
+        set_config_value(&config_path, "general", "frontend", "ibus").unwrap();
+        // 已有键原地替换
+        set_config_value(&config_path, "files", "schema_type", "pro").unwrap();
+        // 不存在的节追加到末尾
+        set_config_value(&config_path, "deploy", "command", "none").unwrap();
+
+        let content = std::fs::read_to_string(&config_path).unwrap();
+        assert_eq!(
+            content,
+            "[general]\n# 镜像\nmirror = \"gh-proxy.com\"\nfrontend = \"ibus\"\n\n[files]\nschema_type = \"pro\"\n\n[deploy]\ncommand = \"none\"\n"
+        );
+
+        std::fs::remove_file(&config_path).ok();
+    }
+
+    #[test]
+    fn test_set_pin() {
+        let config_path = std::env::temp_dir().join("test_set_pin.ini");
+        std::fs::write(
+            &config_path,
+            "[files]\nschema_type = \"base\"\nschema_name = \"rime-wanxiang-base.zip\"\n",
+        )
+        .unwrap();
+        let mut config = UpdateConfig::default();
+
+        set_pin(&config_path, &mut config, "schema", "v10.0.0").unwrap();
+        set_pin(&config_path, &mut config, "dict", "123").unwrap();
+        assert_eq!(config.pins.dict_asset_id, Some(123));
+        set_pin(&config_path, &mut config, "dict", "2025-06-01").unwrap();
+        assert!(set_pin(&config_path, &mut config, "dict", "yesterday").is_err());
+
+        let read = read_config(&config_path, false);
+        assert_eq!(read.pins.schema_version, "v10.0.0");
+        assert_eq!(read.pins.dict_asset_id, None);
+        assert_eq!(read.pins.dict_date, "2025-06-01");
+
+        set_pin(&config_path, &mut config, "schema", "").unwrap();
+        assert!(read_config(&config_path, false)
+            .pins
+            .schema_version
+            .is_empty());
+
+        std::fs::remove_file(&config_path).ok();
+    }
+
+    #[test]
+    fn test_read_path_overrides_ignores_7z() {
+        let config_path = std::env::temp_dir().join("test_read_path_overrides.ini");
+        std::fs::write(
+            &config_path,
+            "[paths]\ncurl = \"/usr/bin/curl\"\n7z = \"/opt/7zz\"\n",
+        )
+        .unwrap();
+        let overrides = read_path_overrides(&config_path);
+        assert_eq!(overrides.curl, Some(PathBuf::from("/usr/bin/curl")));
+        let _ = std::fs::remove_file(&config_path);
+    }
+
+    #[test]
+    fn test_split_args() {
+        assert_eq!(split_args(""), Vec::<String>::new());
+        assert_eq!(
+            split_args("--deploy  'my dir' ''"),
+            vec!["--deploy", "my dir", ""]
+        );
+    }
+}
@@ -1,35 +1,146 @@
-use std::{path::Path, process::Command};
+use sha3::{Digest, Sha3_256};
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    path::Path,
+};
 
-/// 使用系统自带的 PowerShell 计算文件的 SHA3-256 哈希值
+/// 超过该大小的文件在计算哈希时显示进度
+const PROGRESS_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// 检查文件开头是否为网页时读取的字节数
+const SNIFF_LEN: usize = 512;
+
+/// 镜像站返回的错误页面、验证页面常见的开头（小写）
+const PAGE_PREFIXES: [&[u8]; 7] = [
+    b"<!doctype html",
+    b"<html",
+    b"<head",
+    b"<body",
+    b"<?xml",
+    b"<!--",
+    b"{\"",
+];
+
+/// 下载文件的格式，按文件名推断
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FileFormat {
+    Zip,
+    /// 语言模型，没有固定的文件头，只检查不是网页
+    Gram,
+    /// Windows 可执行文件
+    Exe,
+    /// 其他文件，只检查不是网页
+    #[default]
+    Unknown,
+}
+
+impl FileFormat {
+    pub fn from_file_name(name: &str) -> Self {
+        let name = name.to_lowercase();
+        if name.ends_with(".zip") {
+            FileFormat::Zip
+        } else if name.ends_with(".gram") {
+            FileFormat::Gram
+        } else if name.ends_with(".exe") {
+            FileFormat::Exe
+        } else {
+            FileFormat::Unknown
+        }
+    }
+}
+
+/// 检查文件内容是否符合格式，并排除镜像站返回的网页
+///
+/// ZIP 文件会读取中央目录，截断的文件无法通过
+pub fn verify_format(file_path: &Path, format: FileFormat) -> Result<(), String> {
+    let mut file = File::open(file_path).map_err(|e| format!("无法打开文件: {}", e))?;
+    let mut head = Vec::with_capacity(SNIFF_LEN);
+    (&mut file)
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut head)
+        .map_err(|e| format!("无法读取文件: {}", e))?;
+
+    if looks_like_page(&head) {
+        return Err("内容是网页或错误信息，不是文件本身".to_string());
+    }
+
+    match format {
+        FileFormat::Zip => {
+            if !head.starts_with(b"PK\x03\x04") && !head.starts_with(b"PK\x05\x06") {
+                return Err("不是 ZIP 文件".to_string());
+            }
+            zip::ZipArchive::new(file).map_err(|e| format!("ZIP 文件不完整: {}", e))?;
+        }
+        FileFormat::Exe if !head.starts_with(b"MZ") => {
+            return Err("不是可执行文件".to_string());
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// 跳过 BOM 和空白后，是否以 HTML、XML 或 JSON 开头
+fn looks_like_page(head: &[u8]) -> bool {
+    let head = head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(head);
+    let start = head
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(head.len());
+    let head = head[start..].to_ascii_lowercase();
+    PAGE_PREFIXES.iter().any(|prefix| head.starts_with(prefix))
+}
+
+/// 流式读取文件并计算 SHA3-256 哈希值
 pub fn calculate_sha3_256(file_path: &Path) -> Result<String, Box<dyn std::error::Error>> {
     println!("🔍 正在计算文件 SHA3-256 校验和...");
 
-    // 使用 PowerShell 的 Get-FileHash 命令计算 SHA3-256
-    let output = Command::new("powershell")
-        .args(&[
-            "-NoProfile",
-            "[System.Console]::OutputEncoding = [System.Console]::InputEncoding = [System.Text.Encoding]::UTF8;",
-            "-Command",
-            &format!(
-                "[System.Console]::OutputEncoding = [System.Console]::InputEncoding = [System.Text.Encoding]::UTF8;Get-FileHash -Path '{}' -Algorithm SHA3-256 | Select-Object -ExpandProperty Hash",
-                file_path.display()
-            ),
-        ])
-        .output()?;
-
-    if output.status.success() {
-        let hash = String::from_utf8(output.stdout)?.trim().to_lowercase();
-
-        if hash.is_empty() {
-            return Err("PowerShell 返回空的哈希值".into());
+    let hash = hash_file(file_path)?;
+
+    println!("✅ 文件 SHA3-256: {}", hash);
+    Ok(hash)
+}
+
+/// 计算文件的 SHA3-256 哈希值，不输出提示信息
+pub fn hash_file(file_path: &Path) -> io::Result<String> {
+    let mut file = File::open(file_path)?;
+    let total = file.metadata()?.len();
+    hash_reader(&mut file, total)
+}
+
+fn hash_reader(reader: &mut dyn Read, total: u64) -> io::Result<String> {
+    let mut hasher = Sha3_256::new();
+    let mut buffer = vec![0u8; 256 * 1024];
+    let mut processed = 0u64;
+    let mut last_percent = None;
+    let show_progress = total >= PROGRESS_THRESHOLD;
+
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
         }
+        hasher.update(&buffer[..n]);
+        processed += n as u64;
 
-        println!("✅ 文件 SHA3-256: {}", hash);
-        Ok(hash)
-    } else {
-        let error = String::from_utf8_lossy(&output.stderr);
-        Err(format!("PowerShell 计算哈希失败: {}", error).into())
+        if show_progress {
+            let percent = processed * 100 / total;
+            if last_percent != Some(percent) {
+                last_percent = Some(percent);
+                print!("\r校验进度: {:>3}%", percent);
+                let _ = io::stdout().flush();
+            }
+        }
     }
+    if show_progress {
+        println!();
+    }
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
 }
 
 /// 验证文件的 SHA3-256 校验和
@@ -76,4 +187,47 @@ mod tests {
         // 清理
         fs::remove_file(&temp_file).ok();
     }
+
+    #[test]
+    fn test_verify_format() {
+        let dir = std::env::temp_dir().join(format!("rime_format_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let check = |name: &str, data: &[u8]| {
+            let path = dir.join(name);
+            fs::write(&path, data).unwrap();
+            verify_format(&path, FileFormat::from_file_name(name))
+        };
+
+        let mut zip_data = Vec::new();
+        {
+            let mut writer = zip::ZipWriter::new(std::io::Cursor::new(&mut zip_data));
+            writer
+                .start_file("a.txt", zip::write::SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(b"hello").unwrap();
+            writer.finish().unwrap();
+        }
+        assert!(check("a.zip", &zip_data).is_ok());
+        assert!(check("a.zip", &zip_data[..zip_data.len() - 10]).is_err());
+        assert!(check("a.zip", b"\n  <!DOCTYPE html><html>captcha</html>").is_err());
+        assert!(check("a.gram", b"\xEF\xBB\xBF<html>").is_err());
+        assert!(check("a.gram", b"\x00\x01binary").is_ok());
+        assert!(check("a.exe", b"MZ\x90\x00").is_ok());
+        assert!(check("a.exe", b"{\"message\":\"Not Found\"}").is_err());
+        assert!(check("updater", b"\x7fELF").is_ok());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_hash_reader_known_vectors() {
+        assert_eq!(
+            hash_reader(&mut &b""[..], 0).unwrap(),
+            "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"
+        );
+        assert_eq!(
+            hash_reader(&mut &b"abc"[..], 3).unwrap(),
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+        );
+    }
 }
@@ -6,27 +6,36 @@
 /// - 检查并更新模型文件
 /// - 支持程序自身更新
 /// - 支持单实例运行
-/// - 支持自动重新部署小狼毫
-use std::{fs, os::windows::process::CommandExt, path::PathBuf};
+/// - 支持自动重新部署小狼毫、fcitx5-rime
+use std::{path::Path, rc::Rc};
 
+mod cli;
 mod config_read;
 mod file_checker;
 mod path_get;
+mod platform;
+mod run_log;
 mod types;
 mod update_checker;
+mod version;
 
 use config_read::read_config;
-use update_checker::core::UpdateChecker;
+use update_checker::{
+    core::UpdateChecker,
+    local_transport::{LocalServer, LocalTransport},
+    mirror::{self, ProbeCache},
+};
 
-use crate::types::{UpdateConfig, UpdateInfo, VERSION};
+use crate::types::{ConflictPolicy, UpdateConfig, UpdateInfo, VERSION};
 
 const PROCESS_ID: &str = "3A5583B7F6A5CF24D2E7C8650277DBB4";
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = cli::CliArgs::from_env();
+
     let instance = single_instance::SingleInstance::new(PROCESS_ID)?;
     if !instance.is_single() {
-        let _ = std::process::Command::new("mshta")
-            .raw_arg("\"javascript:var sh=new ActiveXObject('WScript.Shell'); sh.Popup('检测到程序已在运行',0,'错误',16);close()\"").spawn();
+        platform::show_error_popup("错误", "检测到程序已在运行");
         eprintln!("❌ 程序已在运行！");
         std::process::exit(1);
     }
@@ -43,23 +52,76 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         VERSION
     );
 
-    let paths = match path_get::get_path() {
+    let paths = match path_get::get_path(&args.paths, args.config.as_deref(), args.portable) {
         Ok(p) => p,
         Err(e) => {
             eprintln!("获取路径失败: {}", e);
             std::process::exit(1);
         }
     };
-    let config = read_config(&paths.config);
+    let mut config = read_config(&paths.config, paths.config_created);
+    for (component, value) in &args.pins {
+        match config_read::set_pin(&paths.config, &mut config, component, value) {
+            Ok(()) if value.is_empty() => println!("📌 已取消固定 {}", component),
+            Ok(()) => println!("📌 已将 {} 固定到 {}", component, value),
+            Err(e) => {
+                eprintln!("❌ 无法固定 {}: {}", component, e);
+                std::process::exit(2);
+            }
+        }
+    }
 
-    println!("小狼毫路径: {:?}", paths.weasel);
+    println!("前端: {:?}", paths.frontend);
+    println!("前端路径: {:?}", paths.frontend_root);
     println!("用户目录: {:?}", paths.user);
+    println!("共享数据目录: {:?}", paths.shared);
     println!("配置文件: {:?}", paths.config);
     println!("cURL路径: {:?}", paths.curl);
-    println!("7z路径: {:?}", paths.zip);
+    println!("缓存目录: {:?}", paths.cache);
+    println!("状态目录: {:?}", paths.state);
+
+    match run_log::init(&paths.logs) {
+        Ok(log_path) => println!("运行日志: {:?}", log_path),
+        Err(e) => eprintln!("⚠️ 无法创建运行日志: {}", e),
+    }
 
     // 创建更新检查器
-    let checker = UpdateChecker::new(&paths, config.clone());
+    let checker = if let Some(root) = &args.local_root {
+        println!("使用本地目录中的 Release 数据: {:?}", root);
+        UpdateChecker::with_transport(&paths, config.clone(), Rc::new(LocalTransport::new(root)))
+    } else if let Some(root) = &args.local_server {
+        let server = LocalServer::start(root)?;
+        UpdateChecker::with_transport(&paths, config.clone(), Rc::new(server.transport()))
+    } else {
+        UpdateChecker::new(&paths, config.clone())
+    };
+
+    // 上次运行在替换文件途中退出时先恢复原文件
+    for target in [paths.user.clone(), paths.user.join("dicts")] {
+        checker.recover_interrupted(&target);
+    }
+
+    if let cli::Command::Rollback {
+        component,
+        snapshot,
+    } = &args.command
+    {
+        if checker.rollback(component.as_deref(), snapshot.as_deref()) {
+            println!("\n正在重新部署 {}...", checker.frontend_name());
+            if checker.deploy_frontend() {
+                run_log::record("回滚后部署成功");
+            } else {
+                println!("❌ 部署失败，请手动重新部署");
+                run_log::record("回滚后部署失败");
+            }
+        }
+        return Ok(());
+    }
+
+    if args.command == cli::Command::ProbeMirrors {
+        print_probe_results(&checker.probe_mirrors());
+        return Ok(());
+    }
 
     // 检查所有更新
     println!("\n正在检查更新...");
@@ -68,6 +130,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             let mut has_updates = false;
             if updates.is_empty() {
                 println!("所有组件都是最新版本！");
+                run_log::record("所有组件都是最新版本");
             } else {
                 println!("发现 {} 个更新:", updates.len());
                 for (component, info) in &updates {
@@ -78,72 +141,169 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         format_file_size(info.file_size)
                     );
 
-                    // 处理各个组件的更新
-                    match component.as_str() {
-                        "schema" => {
-                            if perform_update(&checker, info, &paths.user, "方案", &config) {
-                                has_updates = true;
-                            }
-                        }
+                    // 处理各个组件的更新，记录本次写入的目标路径
+                    let (applied, targets) = match component.as_str() {
+                        "schema" => (
+                            perform_update(&checker, component, info, &paths.user, "方案", &config),
+                            vec![paths.user.clone()],
+                        ),
                         "dict" => {
-                            if perform_update(
-                                &checker,
-                                info,
-                                &paths.user.join("dicts"),
-                                "词库",
-                                &config,
-                            ) {
-                                has_updates = true;
-                            }
+                            let dict_path = paths.user.join("dicts");
+                            (
+                                perform_update(
+                                    &checker, component, info, &dict_path, "词库", &config,
+                                ),
+                                vec![dict_path],
+                            )
                         }
                         "model" => {
                             let model_path = paths.user.join(&info.file_name);
-                            if download_and_replace(&checker, info, &model_path, &config) {
-                                has_updates = true;
-                            }
+                            (
+                                download_and_replace(&checker, info, &model_path, &config),
+                                vec![model_path],
+                            )
                         }
                         "self" => {
                             println!("发现程序更新，正在准备自动更新...");
                             if perform_self_update(&checker, info, &config) {
                                 println!("✅ 程序将在更新后重新启动");
-                                return Ok(()); // 程序退出，让批处理脚本接管
+                                run_log::record(&format!("程序自更新已启动: {}", info.tag));
+                                let targets = std::env::current_exe().into_iter().collect();
+                                if let Err(e) =
+                                    checker.record_result(component, info, targets, true)
+                                {
+                                    eprintln!("⚠️ 保存 {} 安装状态失败: {}", component, e);
+                                }
+                                return Ok(()); // 程序退出，让更新脚本接管
                             } else {
                                 println!("❌ 自动更新失败，请手动下载更新:");
                                 println!("  下载地址: {}", info.url);
                             }
+                            (false, Vec::new())
                         }
                         _ => {
                             eprintln!("⚠️ 未知的组件类型: {}", component);
+                            continue;
                         }
-                    }
-                }
+                    };
 
-                // 保存更新信息到缓存
-                for (component, info) in &updates {
-                    let cache_path = checker.cache_dir.join(format!("{}_info.json", component));
-                    if let Err(e) = checker.save_update_info(info, &cache_path) {
-                        eprintln!("保存 {} 更新信息失败: {}", component, e);
+                    run_log::record(&format!(
+                        "{} {} ({}): {}",
+                        component,
+                        info.tag,
+                        info.file_name,
+                        if applied {
+                            "更新成功"
+                        } else {
+                            "更新失败"
+                        }
+                    ));
+
+                    // 只有成功应用后才记录为已安装
+                    if let Err(e) = checker.record_result(component, info, targets, applied) {
+                        eprintln!("⚠️ 保存 {} 安装状态失败: {}", component, e);
                     }
+                    has_updates |= applied;
                 }
             }
 
             if has_updates {
-                println!("\n正在重新部署...");
-                if checker.deploy_weasel() {
+                println!("\n正在重新部署 {}...", checker.frontend_name());
+                if checker.deploy_frontend() {
                     println!("✅ 更新完成!");
+                    run_log::record("部署成功");
                 } else {
-                    println!("❌ 部署失败，请手动重新部署");
+                    println!("❌ 部署失败，正在恢复更新前的文件...");
+                    run_log::record("部署失败");
+                    if checker.revert_applied() && checker.deploy_frontend() {
+                        println!("✅ 已恢复到更新前的版本并重新部署");
+                        run_log::record("已恢复到更新前的版本并重新部署");
+                    } else {
+                        println!("❌ 恢复后仍无法部署，请手动重新部署");
+                        run_log::record("恢复后部署失败");
+                    }
                 }
             }
+
+            print_download_sources(&checker);
+            print_conflicts(&checker);
         }
         Err(e) => {
             eprintln!("检查更新时出错: {}", e);
+            run_log::record(&format!("检查更新时出错: {}", e));
         }
     }
 
     Ok(())
 }
 
+/// 按速度列出镜像测速结果
+fn print_probe_results(cache: &ProbeCache) {
+    println!("\n测速结果:");
+    for (i, result) in cache.results.iter().enumerate() {
+        match result.latency_ms {
+            Some(latency) if result.is_healthy() => println!(
+                "  {}. {} - {} ms, {:.1} KB/s",
+                i + 1,
+                mirror::display_name(&result.name),
+                latency,
+                result.throughput as f64 / 1024.0
+            ),
+            _ => println!(
+                "  {}. {} - 不可用",
+                i + 1,
+                mirror::display_name(&result.name)
+            ),
+        }
+    }
+
+    match cache.ranked_mirrors().first() {
+        Some(fastest) => println!("\n✅ 最快的镜像: {}", mirror::display_name(fastest)),
+        None => println!("\n✅ 直连 GitHub 最快，无需使用镜像"),
+    }
+    println!("设置 mirrors = \"auto\" 后将自动使用测速结果");
+}
+
+/// 汇总本次运行中各文件的下载源
+fn print_download_sources(checker: &UpdateChecker) {
+    let sources = checker.download_sources();
+    if sources.is_empty() {
+        return;
+    }
+
+    println!("\n下载来源:");
+    for (file_name, source) in &sources {
+        println!("  {} <- {}", file_name, source);
+    }
+}
+
+/// 汇总本次运行中与用户修改冲突的文件
+fn print_conflicts(checker: &UpdateChecker) {
+    let conflicts = checker.conflicts();
+    if conflicts.is_empty() {
+        return;
+    }
+
+    println!(
+        "\n⚠️ 以下 {} 个文件被你修改过，与新版本冲突:",
+        conflicts.len()
+    );
+    for conflict in &conflicts {
+        let line = match conflict.policy {
+            ConflictPolicy::KeepUser => format!(
+                "{}: {:?} 保留了你的版本，新版本在 {:?}",
+                conflict.component, conflict.path, conflict.saved_as
+            ),
+            ConflictPolicy::Backup => format!(
+                "{}: {:?} 已使用新版本，你的版本备份在 {:?}",
+                conflict.component, conflict.path, conflict.saved_as
+            ),
+        };
+        println!("  {}", line);
+        run_log::record(&format!("文件冲突 {}", line));
+    }
+}
+
 fn format_file_size(size: u64) -> String {
     const UNITS: &[&str] = &["B", "KB", "MB", "GB"];
     let mut size = size as f64;
@@ -159,8 +319,9 @@ fn format_file_size(size: u64) -> String {
 
 fn perform_update(
     checker: &UpdateChecker,
+    component: &str,
     update: &UpdateInfo,
-    extract_path: &PathBuf,
+    extract_path: &Path,
     update_type: &str,
     config: &UpdateConfig,
 ) -> bool {
@@ -170,6 +331,7 @@ fn perform_update(
     if !checker.download_file(
         &update.url,
         &download_path,
+        update.file_size,
         update.sha3_256.as_deref(),
         config.github_cookies.clone(),
     ) {
@@ -177,25 +339,8 @@ fn perform_update(
         return false;
     }
 
-    // 校验下载的文件
-    if let Some(ref expected_hash) = update.sha3_256 {
-        println!("开始校验文件完整性...");
-        match file_checker::verify_sha3_256(&download_path, expected_hash) {
-            Ok(true) => println!("✅ 文件校验通过"),
-            Ok(false) => {
-                eprintln!("❌ 文件校验失败，可能文件已损坏");
-                return false;
-            }
-            Err(e) => {
-                eprintln!("⚠️ 文件校验时出错: {}，继续处理", e);
-            }
-        }
-    } else {
-        println!("⚠️ 未提供校验和，跳过文件完整性校验");
-    }
-
-    // 解压文件
-    if !checker.extract_zip(&download_path, extract_path) {
+    // 解压并替换文件
+    if !checker.install_zip(component, update, &download_path, extract_path) {
         eprintln!("❌ {} 解压失败", update_type);
         return false;
     }
@@ -207,7 +352,7 @@ fn perform_update(
 fn download_and_replace(
     checker: &UpdateChecker,
     update: &UpdateInfo,
-    target_path: &PathBuf,
+    target_path: &Path,
     config: &UpdateConfig,
 ) -> bool {
     let download_path = checker.cache_dir.join(&update.file_name);
@@ -216,6 +361,7 @@ fn download_and_replace(
     if !checker.download_file(
         &update.url,
         &download_path,
+        update.file_size,
         update.sha3_256.as_deref(),
         config.github_cookies.clone(),
     ) {
@@ -223,23 +369,9 @@ fn download_and_replace(
         return false;
     }
 
-    // 验证哈希
-    if let Some(ref expected_hash) = update.sha3_256 {
-        match file_checker::verify_sha3_256(&download_path, expected_hash) {
-            Ok(true) => println!("✅ 模型文件校验通过"),
-            Ok(false) => {
-                eprintln!("❌ 模型文件完整性验证失败");
-                return false;
-            }
-            Err(e) => {
-                eprintln!("⚠️ 模型文件校验时出错: {}，继续处理", e);
-            }
-        }
-    }
-
     // 替换文件
-    if let Err(e) = fs::copy(&download_path, target_path) {
-        eprintln!("❌ 替换模型文件失败: {}", e);
+    if !checker.install_file("model", update, &download_path, target_path) {
+        eprintln!("❌ 替换模型文件失败");
         return false;
     }
 
@@ -258,6 +390,7 @@ fn perform_self_update(
     if !checker.download_file(
         &update.url,
         &download_path,
+        update.file_size,
         update.sha3_256.as_deref(),
         config.github_cookies.clone(),
     ) {
@@ -265,44 +398,6 @@ fn perform_self_update(
         return false;
     }
 
-    // 验证哈希
-    if let Some(ref expected_hash) = update.sha3_256 {
-        match file_checker::verify_sha3_256(&download_path, expected_hash) {
-            Ok(true) => println!("✅ 程序更新文件校验通过"),
-            Ok(false) => {
-                eprintln!("❌ 程序更新文件完整性验证失败");
-                return false;
-            }
-            Err(e) => {
-                eprintln!("⚠️ 程序更新文件校验时出错: {}，继续处理", e);
-            }
-        }
-    }
-
-    // 创建自更新脚本
-    let script_content = format!(
-        r#"@echo off
-timeout /t 3 /nobreak >nul
-copy /y "{}" "{}"
-if %errorlevel% equ 0 (
-    echo 更新成功，正在重新启动程序...
-    start "" "{}"
-) else (
-    echo 更新失败！
-    pause
-)
-del "%~f0""#,
-        download_path.display(),
-        std::env::current_exe().unwrap().display(),
-        std::env::current_exe().unwrap().display()
-    );
-
-    let script_path = checker.cache_dir.join("update.bat");
-    if fs::write(&script_path, script_content).is_ok() {
-        // 启动更新脚本
-        let _ = std::process::Command::new(&script_path).spawn();
-        return true;
-    }
-
-    false
+    // 启动自更新
+    platform::spawn_self_update(&download_path, &checker.cache_dir)
 }
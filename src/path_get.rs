@@ -1,142 +1,273 @@
-use crate::types::UserPath;
+use crate::config_read;
+use crate::types::{PathOverrides, UserPath};
+use crate::update_checker::frontend::{self, FrontendKind};
+use ini::Ini;
 use rust_embed::Embed;
-use std::{os::windows::process::CommandExt, path::PathBuf, process::Command};
+use std::path::{Path, PathBuf};
 
 const CONF_FILENAME: &str = "updater_conf.ini";
+const APP_DIR_NAME: &str = "rime_wanxiang_updater";
+/// 程序目录下存在该文件时启用便携模式
+const PORTABLE_MARKER: &str = "portable";
 
-pub fn get_path() -> Result<UserPath, Box<dyn std::error::Error>> {
-    let (user_path, config_path) = get_user_path()?;
-    let (weasel_path, curl_path, zip_path) = get_exe_path()?;
-    Ok(UserPath {
-        zip: zip_path,
-        curl: curl_path,
-        weasel: weasel_path,
-        user: user_path,
-        config: config_path,
-    })
+/// 配置、缓存、状态和日志的存放位置
+struct StorageLayout {
+    /// 为 None 时配置文件位于 Rime 用户目录中
+    config: Option<PathBuf>,
+    /// 为 None 时状态和日志位于 Rime 用户目录中
+    data: Option<DataDirs>,
 }
 
-fn get_user_path() -> Result<(PathBuf, PathBuf), Box<dyn std::error::Error>> {
-    let output = Command::new("powershell")
-        .args([
-            "-NoProfile",
-            "-Command",
-            "[System.Console]::OutputEncoding = [System.Console]::InputEncoding = [System.Text.Encoding]::UTF8;",
-            "Get-ItemProperty", 
-            "-Path" ,
-            "'Registry::HKEY_CURRENT_USER\\Software\\Rime\\Weasel'"
-        ])
-        .creation_flags(0x08000000)
-        .output()
-        .expect("未安装 PowerShell 或调用失败");
-
-    if output.status.success() {
-        let user = parse_user_path(
-            String::from_utf8(output.stdout).expect("Failed to convert output to string"),
-        );
-        let config = format!("{}/{}", user, CONF_FILENAME);
-        let packed_config = PathBuf::from(config);
-        config_exist(&packed_config);
-
-        Ok((PathBuf::from(user), packed_config))
-    } else {
-        Err(format!(
-            "Failed to get Rime user directory: {}",
-            String::from_utf8_lossy(&output.stderr)
-        )
-        .into())
-    }
+struct DataDirs {
+    /// 为 None 时缓存位于 Rime 用户目录中
+    cache: Option<PathBuf>,
+    state: PathBuf,
+    logs: PathBuf,
 }
 
-fn get_exe_path() -> Result<(PathBuf, PathBuf, PathBuf), Box<dyn std::error::Error>> {
-    let output = Command::new("powershell")
-        .args([
-            "-NoProfile","-Command",
-            "[System.Console]::OutputEncoding = [System.Console]::InputEncoding = [System.Text.Encoding]::UTF8;",
-            "Get-ItemProperty",
-            "-Path",
-            "'Registry::HKEY_LOCAL_MACHINE\\SOFTWARE\\WOW6432Node\\Rime\\Weasel'",
-        ])
-        .creation_flags(0x08000000)
-        .output()
-        .expect("未安装 PowerShell 或调用失败");
-
-    if output.status.success() {
-        let exe = parse_exe_path(
-            String::from_utf8(output.stdout).expect("Failed to convert output to string"),
-        );
-        let curl = format!("{}/curl.exe", &exe);
-        let zip = format!("{}/7z.exe", &exe);
-        Ok((PathBuf::from(exe), PathBuf::from(curl), PathBuf::from(zip)))
-    } else {
-        Err(format!(
-            "Failed to get Weasel directory: {}",
-            String::from_utf8_lossy(&output.stderr)
-        )
-        .into())
+impl StorageLayout {
+    /// 确定存放位置
+    ///
+    /// - 便携模式（--portable 或程序目录下存在 portable 文件）：全部放在程序旁边
+    /// - Linux：遵循 XDG 规范（$XDG_CONFIG_HOME、$XDG_CACHE_HOME、$XDG_STATE_HOME）
+    /// - Windows：配置和缓存沿用 Rime 用户目录，状态和日志放在 %LOCALAPPDATA% 下
+    ///
+    /// --config 始终优先决定配置文件位置
+    fn resolve(cli_config: Option<&Path>, portable: bool) -> Self {
+        let exe_dir = std::env::current_exe()
+            .ok()
+            .and_then(|p| p.parent().map(|p| p.to_path_buf()));
+        let portable_dir = exe_dir.filter(|dir| portable || dir.join(PORTABLE_MARKER).exists());
+
+        let mut layout = if let Some(base) = portable_dir {
+            println!("便携模式: {:?}", base);
+            StorageLayout {
+                config: Some(base.join(CONF_FILENAME)),
+                data: Some(DataDirs {
+                    cache: Some(base.join("UpdateCache")),
+                    state: base.join("state"),
+                    logs: base.join("logs"),
+                }),
+            }
+        } else if cfg!(target_os = "windows") {
+            let state = std::env::var_os("LOCALAPPDATA")
+                .map(PathBuf::from)
+                .filter(|p| p.is_absolute())
+                .map(|dir| dir.join(APP_DIR_NAME));
+            StorageLayout {
+                config: None,
+                data: state.map(|state| DataDirs {
+                    cache: None,
+                    logs: state.join("logs"),
+                    state,
+                }),
+            }
+        } else {
+            let state = xdg_dir("XDG_STATE_HOME", ".local/state").join(APP_DIR_NAME);
+            StorageLayout {
+                config: Some(
+                    xdg_dir("XDG_CONFIG_HOME", ".config")
+                        .join(APP_DIR_NAME)
+                        .join(CONF_FILENAME),
+                ),
+                data: Some(DataDirs {
+                    cache: Some(xdg_dir("XDG_CACHE_HOME", ".cache").join(APP_DIR_NAME)),
+                    logs: state.join("logs"),
+                    state,
+                }),
+            }
+        };
+
+        if let Some(config) = cli_config {
+            layout.config = Some(config.to_path_buf());
+        }
+        layout
     }
 }
 
-fn parse_user_path(output: String) -> String {
-    let mut result = String::new();
-    for line in output.lines() {
-        if line.contains("RimeUserDir") {
-            let user_path_str = line
-                .split_once(':')
-                .map(|(_, v)| {
-                    let binding = v
-                        .trim()
-                        .replace("\\\\", "/")
-                        .replace("\\", "/")
-                        .replace("//", "/");
-                    format!("{}", binding.trim_end_matches('/'))
-                })
-                .unwrap();
-            result = user_path_str.to_string();
-            break;
+/// 读取 XDG 目录环境变量，未设置或非绝对路径时使用 $HOME 下的默认位置
+fn xdg_dir(var: &str, default: &str) -> PathBuf {
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .unwrap_or_else(|| {
+            std::env::var_os("HOME")
+                .map(PathBuf::from)
+                .unwrap_or_default()
+                .join(default)
+        })
+}
+
+/// 获取所有路径
+///
+/// 优先级：命令行参数 > 配置文件 [paths] 节 > 自动发现
+pub fn get_path(
+    cli_paths: &PathOverrides,
+    cli_config: Option<&Path>,
+    portable: bool,
+) -> Result<UserPath, Box<dyn std::error::Error>> {
+    let layout = StorageLayout::resolve(cli_config, portable);
+
+    // 配置文件位置已知时，先读取其中的 [paths]，使其也能决定用户目录
+    let mut overrides = cli_paths.clone();
+    let mut config_created = false;
+    if let Some(config_path) = &layout.config {
+        config_created = config_exist(config_path);
+        overrides = overrides.or(config_read::read_path_overrides(config_path));
+    }
+
+    let (kind, saved) = detect_frontend(layout.config.as_deref(), overrides.user_dir.as_deref());
+    let located = frontend::locate(kind);
+
+    let user_path = match (&overrides.user_dir, &located) {
+        (Some(dir), _) => dir.clone(),
+        (None, Ok(frontend)) => frontend.user_dir()?,
+        (None, Err(e)) => {
+            return Err(format!(
+                "无法定位{}用户目录: {}\n可使用 --user-dir 手动指定",
+                kind.display_name(),
+                e
+            )
+            .into())
+        }
+    };
+
+    let config_path = match &layout.config {
+        Some(config_path) => config_path.clone(),
+        None => {
+            let config_path = user_path.join(CONF_FILENAME);
+            config_created = config_exist(&config_path);
+            overrides = overrides.or(config_read::read_path_overrides(&config_path));
+            config_path
+        }
+    };
+
+    if !saved {
+        if let Err(e) =
+            config_read::set_config_value(&config_path, "general", "frontend", kind.as_str())
+        {
+            eprintln!("保存前端选择失败: {}", e);
         }
     }
-    result
+
+    let frontend = match (&overrides.frontend_root, located) {
+        (Some(root), _) => frontend::create(kind, root),
+        (None, Ok(frontend)) => frontend,
+        (None, Err(e)) => {
+            return Err(format!(
+                "无法定位{}安装目录: {}\n可使用 --frontend-root 手动指定",
+                kind.display_name(),
+                e
+            )
+            .into())
+        }
+    };
+
+    let user_path = overrides.user_dir.unwrap_or(user_path);
+    let data = layout.data.unwrap_or_else(|| DataDirs {
+        cache: None,
+        state: user_path.join("UpdateState"),
+        logs: user_path.join("UpdateState").join("logs"),
+    });
+    let cache = data.cache.unwrap_or_else(|| user_path.join("UpdateCache"));
+
+    Ok(UserPath {
+        curl: overrides
+            .curl
+            .unwrap_or_else(|| frontend.bundled_tool("curl")),
+        frontend: kind,
+        frontend_root: frontend.root_dir(),
+        shared: frontend.shared_dir().ok(),
+        user: user_path,
+        config: config_path,
+        config_created,
+        cache,
+        state: data.state,
+        logs: data.logs,
+    })
 }
 
-fn parse_exe_path(output: String) -> String {
-    let mut result = String::new();
-    for line in output.lines() {
-        if line.contains("WeaselRoot") {
-            let exe_path_str = line
-                .split_once(':')
-                .map(|(_, v)| {
-                    let binding = v
-                        .trim()
-                        .replace("\\\\", "/")
-                        .replace("\\", "/")
-                        .replace("//", "/");
-                    format!("{}", binding.trim_end_matches('/'))
-                })
-                .unwrap();
-            result = exe_path_str.to_string();
-            break;
+/// 检测已安装的前端并确定要更新的目标
+///
+/// 优先使用配置文件中保存的选择；检测到多个前端时询问用户。
+/// 返回值的第二项表示该选择是否已保存在配置文件中
+fn detect_frontend(config_path: Option<&Path>, user_dir: Option<&Path>) -> (FrontendKind, bool) {
+    let installed = frontend::detect_installed();
+
+    // 配置文件位置已知或手动指定了用户目录时，以对应配置文件中的选择为准，
+    // 否则在各个已检测到的前端的用户目录中查找
+    let known_config = config_path
+        .map(|p| p.to_path_buf())
+        .or_else(|| user_dir.map(|dir| dir.join(CONF_FILENAME)));
+    let saved = known_config
+        .as_deref()
+        .and_then(saved_frontend)
+        .or_else(|| {
+            installed.iter().find_map(|kind| {
+                let user_dir = frontend::locate(*kind).ok()?.user_dir().ok()?;
+                saved_frontend(&user_dir.join(CONF_FILENAME))
+            })
+        });
+
+    // 配置中明确指定的前端即使未被检测到也照常使用
+    if let Some(saved) = saved {
+        if !installed.contains(&saved) {
+            println!(
+                "⚠️ 未检测到配置中指定的前端 {}，仍按配置使用",
+                saved.display_name()
+            );
         }
+        println!("使用已保存的前端: {}", saved.display_name());
+        return (saved, true);
+    }
+
+    match installed.as_slice() {
+        [] => {
+            let kind = FrontendKind::platform_default();
+            println!(
+                "⚠️ 未检测到已安装的前端，使用默认前端: {}",
+                kind.display_name()
+            );
+            (kind, false)
+        }
+        [kind] => {
+            println!("检测到前端: {}", kind.display_name());
+            (*kind, false)
+        }
+        candidates => (config_read::select_frontend(candidates), false),
     }
-    result
 }
 
-fn config_exist(config_path: &PathBuf) {
-    if !config_path.exists() {
-        // 确保目录存在
-        if let Some(parent) = config_path.parent() {
-            if let Err(e) = std::fs::create_dir_all(parent) {
-                eprintln!("创建配置文件目录失败: {}", e);
-                return;
-            }
+/// 读取配置文件中保存的前端选择
+fn saved_frontend(config_path: &Path) -> Option<FrontendKind> {
+    let ini = Ini::load_from_file(config_path).ok()?;
+    let value = ini.section(Some("general"))?.get("frontend")?;
+    FrontendKind::parse(value.trim_matches('"'))
+}
+
+/// 配置文件不存在时写入默认配置，返回是否新建了配置文件
+fn config_exist(config_path: &Path) -> bool {
+    if config_path.exists() {
+        return false;
+    }
+
+    // 确保目录存在
+    if let Some(parent) = config_path.parent() {
+        if let Err(e) = std::fs::create_dir_all(parent) {
+            eprintln!("创建配置文件目录失败: {}", e);
+            return false;
         }
+    }
 
-        #[derive(Embed)]
-        #[folder = "res/"]
-        struct Asset;
-        let ini_res = Asset::get(CONF_FILENAME).expect("Error reading embedded INI resource file");
-        if let Err(e) = std::fs::write(config_path, ini_res.data.as_ref()) {
+    #[derive(Embed)]
+    #[folder = "res/"]
+    struct Asset;
+    let ini_res = Asset::get(CONF_FILENAME).expect("Error reading embedded INI resource file");
+    match std::fs::write(config_path, ini_res.data.as_ref()) {
+        Ok(()) => true,
+        Err(e) => {
             eprintln!("写入配置文件失败: {}", e);
+            false
         }
     }
 }
@@ -147,9 +278,20 @@ mod tests {
 
     #[test]
     fn test_path_correct() {
-        match get_path() {
+        match get_path(&PathOverrides::default(), None, false) {
             Ok(_) => println!("路径获取成功"),
             Err(e) => println!("路径获取失败: {}", e),
         }
     }
+
+    #[test]
+    fn test_detect_frontend_honors_config() {
+        let config_path = std::env::temp_dir().join("test_detect_frontend.ini");
+        std::fs::write(&config_path, "[general]\nfrontend = \"weasel\"\n").unwrap();
+        assert_eq!(
+            detect_frontend(Some(&config_path), None),
+            (FrontendKind::Weasel, true)
+        );
+        let _ = std::fs::remove_file(&config_path);
+    }
 }
@@ -0,0 +1,80 @@
+use std::{
+    fs,
+    os::unix::{fs::PermissionsExt, process::CommandExt},
+    path::{Path, PathBuf},
+    process::{Child, Command},
+};
+
+/// 弹出错误提示框，依次尝试 zenity 和 notify-send
+pub fn show_error_popup(title: &str, message: &str) {
+    let shown = Command::new("zenity")
+        .args(["--error", "--title", title, "--text", message])
+        .spawn()
+        .is_ok();
+    if !shown {
+        let _ = Command::new("notify-send")
+            .args(["-u", "critical", title, message])
+            .spawn();
+    }
+}
+
+/// 强制结束指定名称的进程
+pub fn kill_process(name: &str) {
+    let _ = Command::new("pkill").args(["-KILL", "-x", name]).output();
+}
+
+/// 让子进程成为新进程组的组长，便于连同它启动的进程一起结束
+pub fn new_process_group(command: &mut Command) -> &mut Command {
+    command.process_group(0)
+}
+
+/// 强制结束子进程所在的整个进程组
+pub fn kill_process_tree(child: &mut Child) {
+    let _ = Command::new("kill")
+        .args(["-KILL", "--", &format!("-{}", child.id())])
+        .output();
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Linux 下没有注册表
+pub fn read_registry_value(key: &str, value: &str) -> Result<String, Box<dyn std::error::Error>> {
+    Err(format!("当前平台不支持读取注册表: {}\\{}", key, value).into())
+}
+
+/// 判断 Release 资产是否为本平台的程序文件
+pub fn is_self_asset(name: &str) -> bool {
+    let name = name.to_lowercase();
+    name.contains("linux") && !name.ends_with(".exe")
+}
+
+/// 启动自更新：直接替换可执行文件，等待当前进程退出后重新启动
+///
+/// Linux 允许替换正在运行的可执行文件，因此无需借助脚本完成复制
+pub fn spawn_self_update(new_exe: &Path, _work_dir: &Path) -> bool {
+    let current_exe: PathBuf = match std::env::current_exe() {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("❌ 无法获取当前程序路径: {}", e);
+            return false;
+        }
+    };
+
+    // 先复制到同目录下再重命名，保证替换是原子的
+    let staged = current_exe.with_extension("new");
+    let replaced = fs::copy(new_exe, &staged)
+        .and_then(|_| fs::set_permissions(&staged, fs::Permissions::from_mode(0o755)))
+        .and_then(|_| fs::rename(&staged, &current_exe));
+    if let Err(e) = replaced {
+        eprintln!("❌ 替换程序文件失败: {}", e);
+        let _ = fs::remove_file(&staged);
+        return false;
+    }
+
+    // 等待当前进程退出（释放单实例锁）后再启动新版本
+    Command::new("sh")
+        .args(["-c", "sleep 3; exec \"$0\""])
+        .arg(&current_exe)
+        .spawn()
+        .is_ok()
+}
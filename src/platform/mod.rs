@@ -0,0 +1,14 @@
+//! 平台相关功能
+//!
+//! 注册表读取、弹窗提示、结束进程和程序自更新
+//! 在不同系统上的实现各不相同，统一在此按 `target_os` 选择
+
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(target_os = "windows")]
+pub use windows::*;
+
+#[cfg(not(target_os = "windows"))]
+mod linux;
+#[cfg(not(target_os = "windows"))]
+pub use linux::*;
@@ -0,0 +1,129 @@
+use std::{
+    fs,
+    os::windows::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{Child, Command},
+};
+
+/// CREATE_NO_WINDOW
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+/// CREATE_NEW_PROCESS_GROUP
+const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+
+/// 运行子进程时不弹出控制台窗口
+fn hide_window(command: &mut Command) -> &mut Command {
+    command.creation_flags(CREATE_NO_WINDOW)
+}
+
+/// 弹出错误提示框
+pub fn show_error_popup(title: &str, message: &str) {
+    let _ = Command::new("mshta")
+        .raw_arg(format!(
+            "\"javascript:var sh=new ActiveXObject('WScript.Shell'); sh.Popup('{}',0,'{}',16);close()\"",
+            message, title
+        ))
+        .spawn();
+}
+
+/// 强制结束指定名称的进程
+pub fn kill_process(name: &str) {
+    let _ = hide_window(Command::new("taskkill").args(["/f", "/im", name])).output();
+}
+
+/// 让子进程成为新进程组的组长，便于连同它启动的进程一起结束
+pub fn new_process_group(command: &mut Command) -> &mut Command {
+    command.creation_flags(CREATE_NEW_PROCESS_GROUP)
+}
+
+/// 强制结束子进程及其启动的全部进程
+pub fn kill_process_tree(child: &mut Child) {
+    let _ =
+        hide_window(Command::new("taskkill").args(["/f", "/t", "/pid", &child.id().to_string()]))
+            .output();
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// 通过 PowerShell 读取注册表值
+pub fn read_registry_value(key: &str, value: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let output = hide_window(Command::new("powershell").args([
+        "-NoProfile",
+        "-Command",
+        "[System.Console]::OutputEncoding = [System.Console]::InputEncoding = [System.Text.Encoding]::UTF8;",
+        "Get-ItemProperty",
+        "-Path",
+        &format!("'Registry::{}'", key),
+    ]))
+    .output()
+    .map_err(|e| format!("未安装 PowerShell 或调用失败: {}", e))?;
+
+    if output.status.success() {
+        let stdout = String::from_utf8(output.stdout)?;
+        parse_registry_value(&stdout, value)
+            .ok_or_else(|| format!("注册表 {} 中未找到 {}", key, value).into())
+    } else {
+        Err(format!(
+            "Failed to read registry {}: {}",
+            key,
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into())
+    }
+}
+
+/// 从 Get-ItemProperty 的输出中解析指定注册表值
+fn parse_registry_value(output: &str, value: &str) -> Option<String> {
+    output
+        .lines()
+        .find(|line| line.contains(value))
+        .and_then(|line| line.split_once(':'))
+        .map(|(_, v)| {
+            let binding = v
+                .trim()
+                .replace("\\\\", "/")
+                .replace('\\', "/")
+                .replace("//", "/");
+            binding.trim_end_matches('/').to_string()
+        })
+}
+
+/// 判断 Release 资产是否为本平台的程序文件
+pub fn is_self_asset(name: &str) -> bool {
+    name.to_lowercase().ends_with(".exe")
+}
+
+/// 启动自更新：写入批处理脚本，等待当前进程退出后替换程序并重新启动
+pub fn spawn_self_update(new_exe: &Path, work_dir: &Path) -> bool {
+    let current_exe: PathBuf = match std::env::current_exe() {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("❌ 无法获取当前程序路径: {}", e);
+            return false;
+        }
+    };
+
+    let script_content = format!(
+        r#"@echo off
+timeout /t 3 /nobreak >nul
+copy /y "{}" "{}"
+if %errorlevel% equ 0 (
+    echo 更新成功，正在重新启动程序...
+    start "" "{}"
+) else (
+    echo 更新失败！
+    pause
+)
+del "%~f0""#,
+        new_exe.display(),
+        current_exe.display(),
+        current_exe.display()
+    );
+
+    let script_path = work_dir.join("update.bat");
+    if fs::write(&script_path, script_content).is_ok() {
+        // 启动更新脚本
+        return Command::new(&script_path).spawn().is_ok();
+    }
+
+    false
+}
@@ -0,0 +1,42 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const LOG_FILENAME: &str = "updater.log";
+
+static LOG_FILE: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// 打开运行日志文件（追加写入），并记录本次运行的开始
+pub fn init(log_dir: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    fs::create_dir_all(log_dir)?;
+    let log_path = log_dir.join(LOG_FILENAME);
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)?;
+    let _ = LOG_FILE.set(Mutex::new(file));
+
+    record(&format!("===== 运行开始 (unix {}) =====", unix_now()));
+    Ok(log_path)
+}
+
+/// 写入一行运行日志，日志未初始化时忽略
+pub fn record(line: &str) {
+    if let Some(file) = LOG_FILE.get() {
+        if let Ok(mut file) = file.lock() {
+            let _ = writeln!(file, "[{}] {}", unix_now(), line);
+        }
+    }
+}
+
+/// 当前的 unix 时间（秒）
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
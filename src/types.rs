@@ -1,5 +1,7 @@
+use crate::update_checker::frontend::FrontendKind;
+use crate::version::is_newer_tag;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf};
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -14,6 +16,66 @@ pub struct UpdateInfo {
     pub sha3_256: Option<String>,
     pub update_time: String,
     pub description: String,
+    /// 资产标识，用于检测固定标签（如 dict-nightly、LTS）下资产的变化
+    #[serde(default)]
+    pub asset_id: u64,
+    #[serde(default)]
+    pub asset_updated_at: String,
+    #[serde(default)]
+    pub digest: Option<String>,
+    /// 是否由 [pins] 固定的版本，固定版本允许降级
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+impl UpdateInfo {
+    /// 与已安装的版本比较，返回发生变化的项目，未变化时返回空列表
+    ///
+    /// 标签为版本号时只在远程版本更新时视为变化，固定的版本与已安装的不同即视为变化；
+    /// 标签相同时比较资产 ID、更新时间、大小和摘要，缺少的项不参与比较
+    pub fn changes_since(&self, installed: &UpdateInfo) -> Vec<String> {
+        let mut changes = Vec::new();
+
+        if self.tag != installed.tag {
+            if is_newer_tag(&self.tag, &installed.tag) {
+                changes.push(format!("标签 {} -> {}", installed.tag, self.tag));
+            } else if self.pinned {
+                changes.push(format!("降级到固定版本 {} -> {}", installed.tag, self.tag));
+            }
+            return changes;
+        }
+
+        if installed.asset_id == 0 && installed.asset_updated_at.is_empty() {
+            changes.push("本地记录缺少资产信息".to_string());
+            return changes;
+        }
+        if self.asset_id != 0 && self.asset_id != installed.asset_id {
+            changes.push(format!(
+                "资产 ID {} -> {}",
+                installed.asset_id, self.asset_id
+            ));
+        }
+        if !self.asset_updated_at.is_empty() && self.asset_updated_at != installed.asset_updated_at
+        {
+            changes.push(format!(
+                "资产更新时间 {} -> {}",
+                installed.asset_updated_at, self.asset_updated_at
+            ));
+        }
+        if self.file_size != installed.file_size {
+            changes.push(format!(
+                "大小 {} -> {} bytes",
+                installed.file_size, self.file_size
+            ));
+        }
+        if let (Some(remote), Some(local)) = (&self.digest, &installed.digest) {
+            if remote != local {
+                changes.push(format!("摘要 {} -> {}", local, remote));
+            }
+        }
+
+        changes
+    }
 }
 
 #[derive(Debug, Clone, serde::Deserialize)]
@@ -22,7 +84,16 @@ pub struct UpdateConfig {
     pub dict_repo: String,
     pub model_repo: String,
     pub self_repo: String,
-    pub mirror: String,
+    /// 下载镜像，按顺序尝试，全部失败后直连 GitHub
+    pub mirrors: Vec<String>,
+    /// 每个下载源遇到暂时性错误时最多尝试的次数
+    pub download_retries: u32,
+    /// 自动选择镜像时测速结果的有效时长（小时）
+    pub mirror_probe_ttl: u64,
+    pub frontend: String,
+    pub transport: String,
+    /// 每个组件保留的更新前快照数量，0 表示不创建快照
+    pub snapshot_keep: usize,
     pub schema_type: String,
     pub schema_key: String,
     pub schema_name: String,
@@ -31,15 +102,212 @@ pub struct UpdateConfig {
     pub model_tag: String,
     pub model_file_name: String,
     pub github_cookies: Option<String>,
+    pub deploy: DeployConfig,
+    pub install: InstallConfig,
+    /// 各组件的文件筛选，键为组件名，对应配置文件中的 [filters] 节
+    pub filters: BTreeMap<String, FilterConfig>,
+    pub pins: PinConfig,
+    pub channels: ChannelConfig,
+}
+
+/// 应用更新时的文件处理配置，对应配置文件中的 [install] 节
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct InstallConfig {
+    pub removed_files: RemovedFilePolicy,
+    pub conflicts: ConflictPolicy,
+}
+
+/// 上游已删除的文件的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+pub enum RemovedFilePolicy {
+    /// 直接删除
+    Delete,
+    /// 移动到状态目录的 quarantine 中
+    #[default]
+    Quarantine,
+    /// 保留不动
+    Keep,
+}
+
+impl RemovedFilePolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "delete" => Some(RemovedFilePolicy::Delete),
+            "" | "quarantine" => Some(RemovedFilePolicy::Quarantine),
+            "keep" => Some(RemovedFilePolicy::Keep),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RemovedFilePolicy::Delete => "delete",
+            RemovedFilePolicy::Quarantine => "quarantine",
+            RemovedFilePolicy::Keep => "keep",
+        }
+    }
+}
+
+/// 发布渠道
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+pub enum Channel {
+    /// 只使用正式版本，跳过预发布版本和草稿
+    #[default]
+    Stable,
+    /// 同时接受预发布版本和草稿
+    Beta,
+    /// 跟随滚动更新的标签
+    Nightly,
+}
+
+impl Channel {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "stable" => Some(Channel::Stable),
+            "beta" => Some(Channel::Beta),
+            "nightly" => Some(Channel::Nightly),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Channel::Stable => "stable",
+            Channel::Beta => "beta",
+            Channel::Nightly => "nightly",
+        }
+    }
+}
+
+/// 方案和程序自身的发布渠道，对应配置文件中的 [channels] 节
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ChannelConfig {
+    pub schema: Channel,
+    /// nightly 渠道跟随的方案标签
+    pub schema_nightly_tag: String,
+    pub self_update: Channel,
+    /// nightly 渠道跟随的程序标签
+    pub self_nightly_tag: String,
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        Self {
+            schema: Channel::Stable,
+            schema_nightly_tag: "nightly".to_string(),
+            self_update: Channel::Stable,
+            self_nightly_tag: "nightly".to_string(),
+        }
+    }
+}
+
+/// 固定的版本，对应配置文件中的 [pins] 节，留空表示跟随最新版本
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct PinConfig {
+    /// 方案的版本标签，如 v10.0.0
+    pub schema_version: String,
+    /// 词库资产 ID
+    pub dict_asset_id: Option<u64>,
+    /// 词库资产的更新日期，如 2025-06-01
+    pub dict_date: String,
+    /// 模型的 Release 标签，替代 [files] 中的 model_tag
+    pub model_version: String,
+}
+
+/// 组件安装时的文件筛选，为空时安装全部文件
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct FilterConfig {
+    /// 只安装匹配的文件
+    pub include: Vec<String>,
+    /// 不安装匹配的文件，优先于 include
+    pub exclude: Vec<String>,
+}
+
+/// 用户修改过的文件与新版本冲突时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+pub enum ConflictPolicy {
+    /// 保留用户的文件，新版本写为 `.new`
+    #[default]
+    KeepUser,
+    /// 备份用户的文件后覆盖
+    Backup,
+}
+
+impl ConflictPolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "keep_user" => Some(ConflictPolicy::KeepUser),
+            "backup" => Some(ConflictPolicy::Backup),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConflictPolicy::KeepUser => "keep_user",
+            ConflictPolicy::Backup => "backup",
+        }
+    }
+}
+
+/// 自定义部署命令配置，对应配置文件中的 [deploy] 节
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct DeployConfig {
+    /// 部署命令，留空则使用前端自带的部署方式，"none" 表示不部署
+    pub command: String,
+    pub args: Vec<String>,
+    pub working_dir: Option<PathBuf>,
+    /// 超时时间（秒），0 表示不限制
+    pub timeout: u64,
+    pub expected_exit_code: i32,
+}
+
+impl DeployConfig {
+    /// 是否设置了自定义部署命令（包括 "none"）
+    pub fn is_custom(&self) -> bool {
+        !self.command.trim().is_empty()
+    }
+
+    /// 是否禁用部署
+    pub fn is_disabled(&self) -> bool {
+        self.command.trim().eq_ignore_ascii_case("none")
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct UserPath {
     pub user: PathBuf,
-    pub weasel: PathBuf,
+    pub frontend: FrontendKind,
+    pub frontend_root: PathBuf,
+    pub shared: Option<PathBuf>,
     pub config: PathBuf,
+    /// 配置文件是否在本次运行中新建，新建时启动配置向导
+    pub config_created: bool,
     pub curl: PathBuf,
-    pub zip: PathBuf,
+    pub cache: PathBuf,
+    pub state: PathBuf,
+    pub logs: PathBuf,
+}
+
+/// 手动指定的路径，优先于自动发现
+///
+/// 来自命令行参数或配置文件中的 [paths] 节
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PathOverrides {
+    pub user_dir: Option<PathBuf>,
+    pub frontend_root: Option<PathBuf>,
+    pub curl: Option<PathBuf>,
+}
+
+impl PathOverrides {
+    /// 合并两组路径，self 中已设置的项优先
+    pub fn or(self, other: PathOverrides) -> PathOverrides {
+        PathOverrides {
+            user_dir: self.user_dir.or(other.user_dir),
+            frontend_root: self.frontend_root.or(other.frontend_root),
+            curl: self.curl.or(other.curl),
+        }
+    }
 }
 
 // ===== GitHub API 相关类型 =====
@@ -48,16 +316,38 @@ pub struct UserPath {
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct GitHubRelease {
     pub tag_name: String,
+    /// 草稿的发布时间为 null
+    #[serde(default, deserialize_with = "null_as_default")]
     pub published_at: String,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
     pub body: Option<String>,
     pub assets: Vec<GitHubAsset>,
 }
 
+/// 把 null 视为默认值
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
 /// GitHub Asset 响应结构
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct GitHubAsset {
+    #[serde(default)]
+    pub id: u64,
     pub name: String,
     pub size: u64,
+    #[serde(default)]
+    pub updated_at: String,
+    /// GitHub 提供的摘要，如 "sha256:..."
+    #[serde(default)]
+    pub digest: Option<String>,
     pub browser_download_url: String,
     #[serde(rename = "sha3-256")]
     pub sha3_256: Option<String>,
@@ -80,7 +370,12 @@ impl Default for UpdateConfig {
             dict_repo: "amzxyz/rime_wanxiang".to_string(),
             model_repo: "amzxyz/RIME-LMDG".to_string(),
             self_repo: "Mikachu2333/rime_wanxiang_updater".to_string(),
-            mirror: "".to_string(),
+            mirrors: Vec::new(),
+            download_retries: 3,
+            mirror_probe_ttl: 24,
+            frontend: "".to_string(),
+            transport: "auto".to_string(),
+            snapshot_keep: 3,
             schema_type: "base".to_string(),
             schema_key: "".to_string(),
             schema_name: "rime-wanxiang-base.zip".to_string(),
@@ -89,42 +384,11 @@ impl Default for UpdateConfig {
             model_tag: "LTS".to_string(),
             model_file_name: "wanxiang-lts-zh-hans.gram".to_string(),
             github_cookies: None,
+            deploy: DeployConfig::default(),
+            install: InstallConfig::default(),
+            filters: BTreeMap::new(),
+            pins: PinConfig::default(),
+            channels: ChannelConfig::default(),
         }
     }
 }
-
-pub fn compare_version(remote_info: String, local_info: String) -> bool {
-    dbg!(&remote_info, &local_info);
-    let remote_each = remote_info
-        .splitn(3, '.')
-        .map(|x| {
-            let filtered: String = x.chars().filter(|c| c.is_ascii_digit()).collect();
-            if filtered.is_empty() {
-                0
-            } else {
-                filtered.parse::<u16>().unwrap_or(0)
-            }
-        })
-        .collect::<Vec<u16>>();
-    let local_each = local_info
-        .splitn(3, '.')
-        .map(|x| {
-            let filtered: String = x.chars().filter(|c| c.is_ascii_digit()).collect();
-            if filtered.is_empty() {
-                0
-            } else {
-                filtered.parse::<u16>().unwrap_or(0)
-            }
-        })
-        .collect::<Vec<u16>>();
-    let mut result = false;
-    dbg!(&local_each, &remote_each);
-    for i in 0..remote_each.len() {
-        if remote_each[i] > local_each[i] {
-            result = result || true;
-        } else {
-            result = result || false;
-        }
-    }
-    result
-}
@@ -1,39 +1,121 @@
-use crate::file_checker;
-use crate::types::{compare_version, UpdateConfig, UpdateInfo, UserPath};
-use std::{collections::HashMap, fs, path::PathBuf};
+use crate::types::{ConflictPolicy, InstallConfig, UpdateConfig, UpdateInfo, UserPath};
+use crate::{
+    file_checker::{self, FileFormat},
+    run_log::{self, unix_now},
+};
+use std::{
+    cell::{OnceCell, RefCell},
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
 
 use super::{
-    file_operations::FileOperations, github_client::GitHubClient, weasel_manager::WeaselManager,
+    custom_deploy::CustomDeployFrontend,
+    file_operations::{ExpectedContent, FileOperations, RetryPolicy},
+    frontend::{self, Frontend},
+    github_client::GitHubClient,
+    install_state::{InstallState, STATE_FILENAME},
+    manifest::{self, Conflict, Manifest},
+    mirror::{self, ProbeCache},
+    path_filter::PathFilter,
+    snapshot::{Snapshot, SnapshotStore},
+    staging,
+    transport::{self, Transport, TransportKind},
 };
 
 pub struct UpdateChecker {
     pub cache_dir: PathBuf,
+    state_path: PathBuf,
     pub github_client: GitHubClient,
+    transport: Rc<dyn Transport>,
     file_ops: FileOperations,
-    weasel_mgr: WeaselManager,
+    /// 按顺序尝试的下载镜像，全部失败后直连 GitHub；含 `auto` 时按测速结果选择
+    mirrors: Vec<String>,
+    /// 自动模式下实际使用的镜像，首次下载时确定
+    resolved_mirrors: OnceCell<Vec<String>>,
+    /// 测速使用的资产地址
+    probe_url: String,
+    /// 测速结果的有效时长
+    probe_ttl: Duration,
+    /// 本次运行中各文件实际使用的下载源 (文件名, 下载源)
+    download_sources: RefCell<Vec<(String, String)>>,
+    frontend: Box<dyn Frontend>,
+    snapshots: SnapshotStore,
+    /// 本次运行中应用更新时创建的快照，部署失败时按相反顺序恢复
+    applied_snapshots: RefCell<Vec<Snapshot>>,
+    install: InstallConfig,
+    /// 各组件的文件筛选，未配置的组件安装全部文件
+    filters: HashMap<String, PathFilter>,
+    /// 上游已删除的文件按隔离策略移动到这里
+    quarantine_dir: PathBuf,
+    /// 与新版本冲突的用户文件按备份策略复制到这里
+    backup_dir: PathBuf,
+    /// 本次运行中处理过的文件冲突，用于运行结束时汇总
+    conflicts: RefCell<Vec<Conflict>>,
+    /// 已应用但尚未记录到安装状态的文件清单
+    pending_manifests: RefCell<HashMap<String, Manifest>>,
 }
 
 impl UpdateChecker {
     pub fn new(paths: &UserPath, config: UpdateConfig) -> Self {
-        let cache_dir = paths.user.join("UpdateCache");
-
-        if !paths.curl.exists() {
-            panic!("未找到 curl.exe: {:?}\n请确保小狼毫已正确安装", paths.curl);
-        }
+        let transport_kind = TransportKind::parse(&config.transport).unwrap_or_else(|| {
+            eprintln!("⚠️ 未知的传输方式: {}，使用自动模式", config.transport);
+            TransportKind::Auto
+        });
+        let transport = Rc::from(transport::create(transport_kind, &paths.curl));
+        Self::with_transport(paths, config, transport)
+    }
 
-        if !paths.zip.exists() {
-            panic!("未找到 7z.exe: {:?}\n请确保小狼毫已正确安装", paths.zip);
-        }
+    /// 使用指定的传输实例创建，所有 API 请求和下载都经由该实例
+    pub fn with_transport(
+        paths: &UserPath,
+        config: UpdateConfig,
+        transport: Rc<dyn Transport>,
+    ) -> Self {
+        let cache_dir = paths.cache.clone();
 
         if let Err(e) = fs::create_dir_all(&cache_dir) {
             panic!("无法创建缓存目录 {:?}: {}", cache_dir, e);
         }
 
+        // 配置了自定义部署命令时替换前端自带的部署方式
+        let mut frontend = frontend::create(paths.frontend, &paths.frontend_root);
+        if config.deploy.is_custom() {
+            frontend = Box::new(CustomDeployFrontend::new(frontend, config.deploy.clone()));
+        }
+
+        println!("网络传输方式: {}", transport.name());
+
         Self {
             cache_dir: cache_dir.clone(),
-            github_client: GitHubClient::new(&paths.curl, config.clone()),
-            file_ops: FileOperations::new(&paths.zip),
-            weasel_mgr: WeaselManager::new(&paths.weasel),
+            state_path: paths.state.join(STATE_FILENAME),
+            github_client: GitHubClient::new(transport.clone(), config.clone()),
+            transport,
+            file_ops: FileOperations::new(RetryPolicy {
+                attempts: config.download_retries,
+                ..RetryPolicy::default()
+            }),
+            mirrors: config.mirrors.clone(),
+            resolved_mirrors: OnceCell::new(),
+            probe_url: mirror::probe_url(&config),
+            probe_ttl: Duration::from_secs(config.mirror_probe_ttl * 3600),
+            download_sources: RefCell::new(Vec::new()),
+            frontend,
+            snapshots: SnapshotStore::new(&paths.state.join("snapshots"), config.snapshot_keep),
+            applied_snapshots: RefCell::new(Vec::new()),
+            install: config.install.clone(),
+            filters: config
+                .filters
+                .iter()
+                .map(|(component, filter)| (component.clone(), PathFilter::new(filter)))
+                .collect(),
+            quarantine_dir: paths.state.join("quarantine"),
+            backup_dir: paths.state.join("backups"),
+            conflicts: RefCell::new(Vec::new()),
+            pending_manifests: RefCell::new(HashMap::new()),
         }
     }
 
@@ -42,35 +124,32 @@ impl UpdateChecker {
         &self,
     ) -> Result<HashMap<String, UpdateInfo>, Box<dyn std::error::Error>> {
         let mut updates = HashMap::new();
+        let state = InstallState::load(&self.state_path);
 
         // 检查方案更新
         if let Some(schema_info) = self.github_client.check_schema_update()? {
-            let cache_path = self.cache_dir.join("schema_info.json");
-            if self.should_update(&schema_info, &cache_path) {
+            if self.should_update("schema", &schema_info, &state) {
                 updates.insert("schema".to_string(), schema_info);
             }
         }
 
         // 检查字典更新
         if let Some(dict_info) = self.github_client.check_dict_update()? {
-            let cache_path = self.cache_dir.join("dict_info.json");
-            if self.should_update(&dict_info, &cache_path) {
+            if self.should_update("dict", &dict_info, &state) {
                 updates.insert("dict".to_string(), dict_info);
             }
         }
 
         // 检查模型更新
         if let Some(model_info) = self.github_client.check_model_update()? {
-            let cache_path = self.cache_dir.join("model_info.json");
-            if self.should_update(&model_info, &cache_path) {
+            if self.should_update("model", &model_info, &state) {
                 updates.insert("model".to_string(), model_info);
             }
         }
 
         // 检查程序更新
         if let Some(self_info) = self.github_client.check_self_update()? {
-            let cache_path = self.cache_dir.join("self_info.json");
-            if self.should_update(&self_info, &cache_path) {
+            if self.should_update("self", &self_info, &state) {
                 updates.insert("self".to_string(), self_info);
             }
         }
@@ -78,160 +157,1206 @@ impl UpdateChecker {
         Ok(updates)
     }
 
-    /// 检查是否需要更新 - 同时检查JSON缓存和实际文件是否存在
-    fn should_update(&self, remote_info: &UpdateInfo, local_cache_path: &PathBuf) -> bool {
-        // 如果缓存信息不存在，需要更新
-        if !local_cache_path.exists() {
-            return true;
-        }
-
-        // 检查对应的文件是否存在于缓存目录中
-        let cached_file_path = self.cache_dir.join(&remote_info.file_name);
-        if !cached_file_path.exists() {
-            println!("缓存文件不存在，需要重新下载: {:?}", cached_file_path);
+    /// 根据状态文件中的安装记录判断是否需要更新
+    fn should_update(
+        &self,
+        component: &str,
+        remote_info: &UpdateInfo,
+        state: &InstallState,
+    ) -> bool {
+        let Some(local_info) = state.installed(component) else {
+            println!("🔍 {}: 没有安装记录，需要更新", component);
             return true;
-        }
+        };
 
-        // 读取并比较缓存的更新信息
-        if let Ok(content) = fs::read_to_string(local_cache_path) {
-            if let Ok(local_info) = serde_json::from_str::<UpdateInfo>(&content) {
-                // 使用compare_version函数进行版本比较
-                let needs_update = compare_version(remote_info.tag.clone(), local_info.tag.clone());
-
-                if needs_update {
-                    println!(
-                        "发现新版本，需要更新: {} -> {}",
-                        local_info.tag, remote_info.tag
-                    );
-                }
-
-                return needs_update;
-            }
-        }
-
-        // 无法解析缓存信息，默认需要更新
-        true
+        let changes = remote_info.changes_since(local_info);
+        let message = if changes.is_empty() {
+            format!(
+                "{}: 未变化 (标签 {}, 资产 ID {}, 更新时间 {})",
+                component, remote_info.tag, remote_info.asset_id, remote_info.asset_updated_at
+            )
+        } else {
+            format!("{}: 发现变化，需要更新: {}", component, changes.join("; "))
+        };
+        println!(
+            "{} {}",
+            if changes.is_empty() { "✅" } else { "🔍" },
+            message
+        );
+        run_log::record(&message);
+        !changes.is_empty()
     }
 
-    /// 保存更新信息到本地缓存
-    pub fn save_update_info(
+    /// 记录组件的更新结果
+    ///
+    /// 只有成功时才更新已安装的版本，失败时仅记录最近一次的结果
+    pub fn record_result(
         &self,
+        component: &str,
         info: &UpdateInfo,
-        cache_path: &PathBuf,
+        targets: Vec<PathBuf>,
+        success: bool,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let json_content = serde_json::to_string_pretty(info)?;
-        if let Some(parent) = cache_path.parent() {
-            fs::create_dir_all(parent)?;
+        let manifest = self.pending_manifests.borrow_mut().remove(component);
+        let mut state = InstallState::load(&self.state_path);
+        if success {
+            state.mark_installed(component, info, targets, manifest.unwrap_or_default());
+        } else {
+            state.mark_failed(component, info);
         }
-        fs::write(cache_path, json_content)?;
-        Ok(())
+        state.save(&self.state_path)
     }
 
-    // 委托给其他模块的方法
+    /// 下载文件，依次尝试各镜像和 GitHub
+    ///
+    /// 大小、格式或校验和不符的下载源视为失败并换下一个，expected_size 为 0 时不检查大小。
+    /// 本地已有符合条件的文件时跳过下载
     pub fn download_file(
         &self,
         url: &str,
         save_path: &PathBuf,
+        expected_size: u64,
         expected_sha3_256: Option<&str>,
         cookies: Option<String>,
     ) -> bool {
+        let file_name = save_path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let expected = ExpectedContent {
+            size: expected_size,
+            format: FileFormat::from_file_name(&file_name),
+            sha3_256: expected_sha3_256.map(|h| h.to_string()),
+        };
+        if expected.sha3_256.is_none() {
+            println!("⚠️ 未提供校验和，仅校验文件大小和格式");
+        }
+
         // 如果文件已存在，先校验完整性
         if save_path.exists() {
-            // 检查文件大小，如果太小说明下载失败
-            if let Ok(metadata) = std::fs::metadata(save_path) {
-                if metadata.len() < 1000 {
-                    println!("🔍 发现不完整的文件 ({}字节)，重新下载...", metadata.len());
+            println!("🔍 检查本地文件完整性...");
+            match self.verify_cached(save_path, &expected) {
+                Ok(()) => {
+                    println!("✅ 本地文件校验通过，跳过下载");
+                    return true;
+                }
+                Err(e) => {
+                    println!("❌ 本地文件校验失败: {}，重新下载...", e);
                     if let Err(e) = std::fs::remove_file(save_path) {
-                        eprintln!("警告：删除不完整文件失败: {}", e);
-                    }
-                } else if let Some(expected_hash) = expected_sha3_256 {
-                    println!("🔍 检查本地文件完整性...");
-                    if self.verify_sha3_256(save_path, expected_hash) {
-                        println!("✅ 本地文件校验通过，跳过下载");
-                        return true;
-                    } else {
-                        println!("❌ 本地文件校验失败，重新下载...");
-                        // 删除损坏的文件
-                        if let Err(e) = std::fs::remove_file(save_path) {
-                            eprintln!("警告：删除损坏文件失败: {}", e);
-                        }
-                    }
-                } else {
-                    println!("⚠️ 未提供校验和，但检测到本地文件，验证文件完整性...");
-                    // 即使没有哈希，也要检查文件是否是有效的 ZIP 文件
-                    if self.verify_zip_integrity(save_path) {
-                        println!("✅ 本地文件格式校验通过，跳过下载");
-                        return true;
-                    } else {
-                        println!("❌ 本地文件格式校验失败，重新下载...");
-                        if let Err(e) = std::fs::remove_file(save_path) {
-                            eprintln!("警告：删除损坏文件失败: {}", e);
-                        }
+                        eprintln!("警告：删除损坏文件失败: {}", e);
                     }
                 }
             }
         }
 
-        // 执行下载
-        let download_success =
-            self.file_ops
-                .download_file(&self.github_client.curl_path, url, save_path, cookies);
+        let sources = mirror::sources(self.mirrors(), url);
+        let Some(source) = self.file_ops.download_file(
+            self.transport.as_ref(),
+            &sources,
+            save_path,
+            &expected,
+            cookies,
+        ) else {
+            return false;
+        };
+        println!("✅ 下载文件校验通过");
+        run_log::record(&format!("{} 下载自 {}", file_name, source));
+        self.download_sources.borrow_mut().push((file_name, source));
+        true
+    }
+
+    pub fn verify_sha3_256(&self, file_path: &Path, expected_hash: &str) -> bool {
+        match file_checker::verify_sha3_256(file_path, expected_hash) {
+            Ok(is_valid) => is_valid,
+            Err(e) => {
+                eprintln!("校验失败: {}", e);
+                false
+            }
+        }
+    }
+
+    /// 检查缓存中已有的文件是否可以直接使用
+    fn verify_cached(&self, file_path: &Path, expected: &ExpectedContent) -> Result<(), String> {
+        let size = fs::metadata(file_path).map_err(|e| e.to_string())?.len();
+        if expected.size > 0 && size != expected.size {
+            return Err(format!("文件大小不符: {} / {} bytes", size, expected.size));
+        }
+        file_checker::verify_format(file_path, expected.format)?;
+        match &expected.sha3_256 {
+            Some(hash) if !self.verify_sha3_256(file_path, hash) => Err("校验和不符".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    /// 恢复 target 上次中断的替换，恢复失败时返回 false
+    ///
+    /// 启动时和应用更新前调用，暂存目录在恢复时用于判断哪些文件已经移动
+    pub fn recover_interrupted(&self, target: &Path) -> bool {
+        match staging::recover(target) {
+            Ok(true) => {
+                println!("🔁 {:?} 上次的更新未完成，已恢复原文件", target);
+                run_log::record(&format!("已恢复中断的更新: {:?}", target));
+                true
+            }
+            Ok(false) => true,
+            Err(e) => {
+                eprintln!("❌ 无法恢复 {:?} 上次中断的更新: {}", target, e);
+                false
+            }
+        }
+    }
+
+    /// 解压到目标旁边的暂存目录，创建快照后整体替换到目标目录
+    pub fn install_zip(
+        &self,
+        component: &str,
+        info: &UpdateInfo,
+        zip_path: &Path,
+        target: &Path,
+    ) -> bool {
+        if !self.recover_interrupted(target) {
+            return false;
+        }
+        let staging_dir = staging::sibling_path(target, "staging");
+        if let Err(e) = staging::remove_path(&staging_dir) {
+            eprintln!("❌ 无法清理暂存目录 {:?}: {}", staging_dir, e);
+            return false;
+        }
+
+        let filter = self.filters.get(component).cloned().unwrap_or_default();
+        if !self.file_ops.extract_zip(zip_path, &staging_dir, &filter) {
+            let _ = staging::remove_path(&staging_dir);
+            return false;
+        }
+
+        let files = match staging::list_files(&staging_dir) {
+            Ok(files) => files,
+            Err(e) => {
+                eprintln!("❌ 无法读取暂存目录: {}", e);
+                let _ = staging::remove_path(&staging_dir);
+                return false;
+            }
+        };
+        let manifest = match Manifest::build(target, &staging_dir, &files) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                eprintln!("❌ 无法生成文件清单: {}", e);
+                let _ = staging::remove_path(&staging_dir);
+                return false;
+            }
+        };
 
-        // 下载完成后再次校验
-        if download_success {
-            if let Some(expected_hash) = expected_sha3_256 {
-                println!("🔍 校验下载的文件...");
-                if !self.verify_sha3_256(save_path, expected_hash) {
-                    eprintln!("❌ 下载文件校验失败");
+        let state = InstallState::load(&self.state_path);
+        let conflicts = match self.resolve_conflicts(component, &manifest, &state, &staging_dir) {
+            Ok(conflicts) => conflicts,
+            Err(e) => {
+                eprintln!("❌ 处理文件冲突失败: {}", e);
+                let _ = staging::remove_path(&staging_dir);
+                return false;
+            }
+        };
+        let files = if conflicts.is_empty() {
+            files
+        } else {
+            match staging::list_files(&staging_dir) {
+                Ok(files) => files,
+                Err(e) => {
+                    eprintln!("❌ 无法读取暂存目录: {}", e);
+                    let _ = staging::remove_path(&staging_dir);
                     return false;
                 }
-                println!("✅ 下载文件校验通过");
-            } else {
-                // 没有哈希时，至少验证是否为有效 ZIP
-                if !self.verify_zip_integrity(save_path) {
-                    eprintln!("❌ 下载文件格式校验失败");
-                    return false;
+            }
+        };
+
+        // 上游已删除的文件也放入快照，回滚时一并恢复；被筛选排除的文件不再管理，保留不动
+        let mut removed = self.removed_files(component, &manifest, &state);
+        removed.retain(|relative| filter.allows(relative));
+        let snapshot_files: Vec<PathBuf> = files.iter().chain(&removed).cloned().collect();
+        let Some(snapshot) = self.take_snapshot(component, info, target, &snapshot_files, &state)
+        else {
+            let _ = staging::remove_path(&staging_dir);
+            return false;
+        };
+
+        match staging::apply_staged(&staging_dir, target) {
+            Ok(count) => {
+                println!("✅ 已替换 {} 个文件: {:?}", count, target);
+                self.clean_removed(component, target, &removed);
+                self.conflicts.borrow_mut().extend(conflicts);
+                self.applied(component, snapshot, manifest);
+                true
+            }
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                let _ = staging::remove_path(&staging_dir);
+                self.discard(snapshot);
+                false
+            }
+        }
+    }
+
+    /// 创建快照后以重命名的方式替换单个文件
+    pub fn install_file(
+        &self,
+        component: &str,
+        info: &UpdateInfo,
+        source: &Path,
+        target: &Path,
+    ) -> bool {
+        let (Some(dir), Some(name)) = (target.parent(), target.file_name()) else {
+            eprintln!("❌ 无效的目标路径: {:?}", target);
+            return false;
+        };
+        let mut manifest = Manifest {
+            root: dir.to_path_buf(),
+            ..Default::default()
+        };
+        if let Err(e) = manifest.add(Path::new(name), source) {
+            eprintln!("❌ 无法生成文件清单: {}", e);
+            return false;
+        }
+
+        let state = InstallState::load(&self.state_path);
+        let removed = self.removed_files(component, &manifest, &state);
+        let mut snapshot_files = vec![PathBuf::from(name)];
+        snapshot_files.extend(removed.iter().cloned());
+        let Some(snapshot) = self.take_snapshot(component, info, dir, &snapshot_files, &state)
+        else {
+            return false;
+        };
+
+        match staging::replace_file(source, target) {
+            Ok(()) => {
+                self.clean_removed(component, dir, &removed);
+                self.applied(component, snapshot, manifest);
+                true
+            }
+            Err(e) => {
+                eprintln!("❌ 替换文件 {:?} 失败: {}", target, e);
+                self.discard(snapshot);
+                false
+            }
+        }
+    }
+
+    /// 处理暂存目录中会覆盖用户修改的文件
+    ///
+    /// keep_user 时把暂存的新文件改名为 `.new`，backup 时先把用户的文件复制到备份目录
+    fn resolve_conflicts(
+        &self,
+        component: &str,
+        manifest: &Manifest,
+        state: &InstallState,
+        staging_dir: &Path,
+    ) -> Result<Vec<Conflict>, Box<dyn std::error::Error>> {
+        let conflicts = manifest.conflicts_with(
+            &state.manifest(component),
+            &state.paths_owned_by_others(component),
+        );
+        if conflicts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let policy = self.install.conflicts;
+        let backup_dir = self.backup_dir.join(component).join(unix_now().to_string());
+        let mut resolved = Vec::new();
+        for relative in conflicts {
+            let path = manifest.root.join(&relative);
+            let saved_as = match policy {
+                ConflictPolicy::KeepUser => {
+                    let mut name = relative.clone().into_os_string();
+                    name.push(".new");
+                    let new_relative = PathBuf::from(name);
+                    fs::rename(staging_dir.join(&relative), staging_dir.join(&new_relative))?;
+                    println!(
+                        "⚠️ {} 已被修改，保留你的版本，新版本写为 {}",
+                        relative.display(),
+                        new_relative.display()
+                    );
+                    manifest.root.join(new_relative)
                 }
-                println!("✅ 下载文件格式校验通过");
+                ConflictPolicy::Backup => {
+                    let backup = backup_dir.join(&relative);
+                    if let Some(parent) = backup.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::copy(&path, &backup)?;
+                    println!(
+                        "⚠️ {} 已被修改，备份后使用新版本: {:?}",
+                        relative.display(),
+                        backup
+                    );
+                    backup
+                }
+            };
+            resolved.push(Conflict {
+                component: component.to_string(),
+                path,
+                saved_as,
+                policy,
+            });
+        }
+        Ok(resolved)
+    }
+
+    /// 对候选镜像测速并缓存结果
+    pub fn probe_mirrors(&self) -> ProbeCache {
+        println!("🔍 正在测速: {}", self.probe_url);
+        let cache = mirror::probe(
+            self.transport.as_ref(),
+            &mirror::candidates(&self.mirrors),
+            &self.probe_url,
+        );
+        if let Err(e) = cache.save(&self.cache_dir.join(mirror::PROBE_CACHE_FILENAME)) {
+            eprintln!("⚠️ 无法保存测速结果: {}", e);
+        }
+        run_log::record(&format!(
+            "镜像测速完成，可用镜像: {:?}",
+            cache.ranked_mirrors()
+        ));
+        cache
+    }
+
+    /// 下载时依次尝试的镜像
+    ///
+    /// 自动模式下使用未过期的测速结果，没有时先测速
+    fn mirrors(&self) -> &[String] {
+        if !mirror::is_auto(&self.mirrors) {
+            return &self.mirrors;
+        }
+        self.resolved_mirrors.get_or_init(|| {
+            let cache_path = self.cache_dir.join(mirror::PROBE_CACHE_FILENAME);
+            let cache = ProbeCache::load(&cache_path, self.probe_ttl)
+                .unwrap_or_else(|| self.probe_mirrors());
+            let mirrors = cache.ranked_mirrors();
+            match mirrors.first() {
+                Some(fastest) => println!("🔁 自动选择镜像: {}", mirror::display_name(fastest)),
+                None => println!("🔁 没有比直连更快的镜像，直接从 GitHub 下载"),
             }
+            mirrors
+        })
+    }
+
+    /// 本次运行中下载的文件及其下载源
+    pub fn download_sources(&self) -> Vec<(String, String)> {
+        self.download_sources.borrow().clone()
+    }
+
+    /// 本次运行中处理过的文件冲突
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        self.conflicts.borrow().clone()
+    }
+
+    /// 上次安装过、本次不再包含的文件
+    ///
+    /// 只处理本组件清单中的文件，其他组件安装的和用户自己的文件不受影响
+    fn removed_files(
+        &self,
+        component: &str,
+        manifest: &Manifest,
+        state: &InstallState,
+    ) -> Vec<PathBuf> {
+        manifest.removed_since(
+            &state.manifest(component),
+            &state.paths_owned_by_others(component),
+        )
+    }
+
+    /// 按配置删除、隔离或保留上游已删除的文件
+    fn clean_removed(&self, component: &str, root: &Path, removed: &[PathBuf]) {
+        if removed.is_empty() {
+            return;
         }
+        let message = format!(
+            "{}: 上游删除了 {} 个文件，处理方式: {}",
+            component,
+            removed.len(),
+            self.install.removed_files.as_str()
+        );
+        println!("🔍 {}", message);
+        run_log::record(&message);
 
-        download_success
+        let quarantine_dir = self
+            .quarantine_dir
+            .join(component)
+            .join(unix_now().to_string());
+        manifest::clean_removed(root, removed, self.install.removed_files, &quarantine_dir);
     }
 
-    pub fn verify_sha3_256(&self, file_path: &PathBuf, expected_hash: &str) -> bool {
-        match file_checker::verify_sha3_256(file_path, expected_hash) {
-            Ok(is_valid) => is_valid,
+    /// 为即将覆盖的文件创建快照
+    ///
+    /// 未启用快照时返回 Some(None)；创建失败时返回 None，此时不应继续更新
+    fn take_snapshot(
+        &self,
+        component: &str,
+        info: &UpdateInfo,
+        target: &Path,
+        files: &[PathBuf],
+        state: &InstallState,
+    ) -> Option<Option<Snapshot>> {
+        if !self.snapshots.is_enabled() {
+            return Some(None);
+        }
+        match self.snapshots.create(
+            component,
+            &info.tag,
+            target,
+            files,
+            state.installed(component),
+            &state.manifest(component),
+        ) {
+            Ok(snapshot) => Some(Some(snapshot)),
             Err(e) => {
-                eprintln!("校验失败: {}", e);
-                false
+                eprintln!("❌ 创建 {} 快照失败，取消更新: {}", component, e);
+                None
+            }
+        }
+    }
+
+    fn applied(&self, component: &str, snapshot: Option<Snapshot>, manifest: Manifest) {
+        if let Some(snapshot) = snapshot {
+            self.applied_snapshots.borrow_mut().push(snapshot);
+        }
+        self.pending_manifests
+            .borrow_mut()
+            .insert(component.to_string(), manifest);
+    }
+
+    fn discard(&self, snapshot: Option<Snapshot>) {
+        if let Some(snapshot) = snapshot {
+            self.snapshots.remove(&snapshot);
+        }
+    }
+
+    /// 恢复指定快照，未指定组件或快照 ID 时使用最新的快照
+    pub fn rollback(&self, component: Option<&str>, snapshot_id: Option<&str>) -> bool {
+        let Some(snapshot) = self.snapshots.find(component, snapshot_id) else {
+            eprintln!("❌ 未找到匹配的快照");
+            let available = self.snapshots.list(component);
+            if !available.is_empty() {
+                println!("可用的快照:");
+                for s in &available {
+                    println!("  {} {} (更新到 {} 之前)", s.component, s.id, s.tag);
+                }
             }
+            return false;
+        };
+        self.restore_snapshot(&snapshot)
+    }
+
+    /// 恢复本次运行中应用的全部更新
+    pub fn revert_applied(&self) -> bool {
+        let snapshots: Vec<Snapshot> = self.applied_snapshots.borrow_mut().drain(..).collect();
+        if snapshots.is_empty() {
+            println!("⚠️ 本次更新没有可用的快照，无法自动恢复");
+            return false;
+        }
+        let mut all_restored = true;
+        for snapshot in snapshots.iter().rev() {
+            all_restored &= self.restore_snapshot(snapshot);
+        }
+        all_restored
+    }
+
+    fn restore_snapshot(&self, snapshot: &Snapshot) -> bool {
+        println!("正在恢复 {} 快照 {}...", snapshot.component, snapshot.id);
+        if let Err(e) = self.snapshots.restore(snapshot) {
+            eprintln!("❌ 恢复快照失败: {}", e);
+            run_log::record(&format!(
+                "恢复 {} 快照 {} 失败: {}",
+                snapshot.component, snapshot.id, e
+            ));
+            return false;
+        }
+
+        let mut state = InstallState::load(&self.state_path);
+        state.mark_rolled_back(
+            &snapshot.component,
+            snapshot.previous.as_ref(),
+            &snapshot.previous_manifest,
+        );
+        if let Err(e) = state.save(&self.state_path) {
+            eprintln!("⚠️ 保存安装状态失败: {}", e);
+        }
+        self.snapshots.remove(snapshot);
+
+        let previous = snapshot
+            .previous
+            .as_ref()
+            .map(|p| p.tag.as_str())
+            .unwrap_or("未安装");
+        println!("✅ 已恢复 {} 到 {}", snapshot.component, previous);
+        run_log::record(&format!(
+            "已恢复 {} 快照 {} ({} -> {})",
+            snapshot.component, snapshot.id, snapshot.tag, previous
+        ));
+        true
+    }
+
+    /// 通过当前前端重新部署
+    pub fn deploy_frontend(&self) -> bool {
+        self.frontend.deploy()
+    }
+
+    pub fn frontend_name(&self) -> &'static str {
+        self.frontend.name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Channel, FilterConfig};
+    use crate::update_checker::{
+        local_transport::{LocalServer, LocalTransport},
+        test_support::{fake_content, fake_zip, fake_zip_with, release_json, Fixture},
+        transport::{DownloadResult, HttpResponse},
+    };
+
+    const SCHEMA_REPO: &str = "amzxyz/rime_wanxiang";
+    const SCHEMA_ASSET: &str = "rime-wanxiang-base.zip";
+
+    fn publish_schema(fixture: &Fixture, tag: &str, seed: u8) {
+        let data = fake_zip(seed);
+        let url = fixture.add_asset(SCHEMA_REPO, tag, SCHEMA_ASSET, &data);
+        fixture.set_releases(
+            SCHEMA_REPO,
+            serde_json::json!([release_json(tag, &[(SCHEMA_ASSET, &data, &url, None)])]),
+        );
+    }
+
+    /// 下载并记录为已安装，模拟一次成功的更新
+    fn install(checker: &UpdateChecker, component: &str, info: &UpdateInfo) {
+        let download_path = checker.cache_dir.join(&info.file_name);
+        assert!(checker.download_file(&info.url, &download_path, info.file_size, None, None));
+        checker
+            .record_result(component, info, vec![checker.cache_dir.clone()], true)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_new_schema_tag_detected() {
+        let fixture = Fixture::new("new_schema_tag");
+        publish_schema(&fixture, "v1.0.0", 1);
+
+        let checker = fixture.checker();
+        let updates = checker.check_all_updates().unwrap();
+        let schema = updates.get("schema").expect("首次运行应发现方案更新");
+        assert_eq!(schema.tag, "v1.0.0");
+        install(&checker, "schema", schema);
+
+        // 版本未变化时不再更新
+        let updates = checker.check_all_updates().unwrap();
+        assert!(!updates.contains_key("schema"));
+
+        // 发布新版本后再次发现更新
+        publish_schema(&fixture, "v1.1.0", 2);
+        let updates = checker.check_all_updates().unwrap();
+        assert_eq!(
+            updates.get("schema").map(|i| i.tag.as_str()),
+            Some("v1.1.0")
+        );
+    }
+
+    #[test]
+    fn test_state_independent_of_cache() {
+        let fixture = Fixture::new("state_file");
+        publish_schema(&fixture, "v1.0.0", 1);
+
+        let checker = fixture.checker();
+        let schema = checker.check_all_updates().unwrap()["schema"].clone();
+
+        // 更新失败不会被记录为已安装
+        checker
+            .record_result("schema", &schema, Vec::new(), false)
+            .unwrap();
+        assert!(checker.check_all_updates().unwrap().contains_key("schema"));
+
+        // 成功后清空下载缓存也不需要重新下载
+        install(&checker, "schema", &schema);
+        fs::remove_dir_all(&checker.cache_dir).unwrap();
+        assert!(!checker.check_all_updates().unwrap().contains_key("schema"));
+
+        let state = InstallState::load(&fixture.paths.state.join(STATE_FILENAME));
+        assert_eq!(
+            state.installed("schema").map(|i| i.tag.as_str()),
+            Some("v1.0.0")
+        );
+    }
+
+    #[test]
+    fn test_first_failure_recorded() {
+        let fixture = Fixture::new("first_failure");
+        publish_schema(&fixture, "v1.0.0", 1);
+
+        let checker = fixture.checker();
+        let schema = checker.check_all_updates().unwrap()["schema"].clone();
+        checker
+            .record_result("schema", &schema, Vec::new(), false)
+            .unwrap();
+
+        // 留下失败记录，但仍视为未安装
+        let state = InstallState::load(&fixture.paths.state.join(STATE_FILENAME));
+        let component = &state.components["schema"];
+        assert!(component.installed.is_none());
+        assert!(!component.last_result.success);
+        assert_eq!(component.last_result.tag, "v1.0.0");
+        assert!(state.installed("schema").is_none());
+        assert!(checker.check_all_updates().unwrap().contains_key("schema"));
+    }
+
+    #[test]
+    fn test_rollback_restores_previous_version() {
+        let fixture = Fixture::new("rollback");
+        let checker = fixture.checker();
+        let user = &fixture.paths.user;
+        let schema_file = user.join("wanxiang.schema.yaml");
+
+        let apply = |tag: &str, seed: u8| {
+            publish_schema(&fixture, tag, seed);
+            let info = checker.check_all_updates().unwrap()["schema"].clone();
+            let zip_path = checker.cache_dir.join(&info.file_name);
+            // 没有校验和时会复用缓存中的同名文件
+            let _ = fs::remove_file(&zip_path);
+            assert!(checker.download_file(&info.url, &zip_path, info.file_size, None, None));
+            assert!(checker.install_zip("schema", &info, &zip_path, user));
+            checker
+                .record_result("schema", &info, vec![user.clone()], true)
+                .unwrap();
+        };
+
+        // 首次安装时自定义文件不受影响，回滚删除新增的文件
+        fs::write(user.join("custom.yaml"), "mine").unwrap();
+        apply("v1.0.0", 1);
+        apply("v1.1.0", 2);
+        assert_eq!(
+            fs::read(&schema_file).unwrap(),
+            fake_content("wanxiang.schema.yaml", 2)
+        );
+
+        // 自动恢复本次运行中的全部更新
+        assert!(checker.revert_applied());
+        assert!(!schema_file.exists());
+        assert_eq!(
+            fs::read_to_string(user.join("custom.yaml")).unwrap(),
+            "mine"
+        );
+        let state = InstallState::load(&fixture.paths.state.join(STATE_FILENAME));
+        assert!(state.installed("schema").is_none());
+
+        // rollback 命令恢复最新的快照
+        apply("v1.0.0", 1);
+        apply("v1.1.0", 2);
+        assert!(checker.rollback(Some("schema"), None));
+        assert_eq!(
+            fs::read(&schema_file).unwrap(),
+            fake_content("wanxiang.schema.yaml", 1)
+        );
+        let state = InstallState::load(&fixture.paths.state.join(STATE_FILENAME));
+        assert_eq!(
+            state.installed("schema").map(|i| i.tag.as_str()),
+            Some("v1.0.0")
+        );
+        assert!(!checker.rollback(Some("dict"), None));
+    }
+
+    /// 发布只包含 names 的方案并安装到用户目录
+    fn apply_schema(
+        fixture: &Fixture,
+        checker: &UpdateChecker,
+        tag: &str,
+        names: &[&str],
+        seed: u8,
+    ) {
+        let data = fake_zip_with(names, seed);
+        let url = fixture.add_asset(SCHEMA_REPO, tag, SCHEMA_ASSET, &data);
+        fixture.set_releases(
+            SCHEMA_REPO,
+            serde_json::json!([release_json(tag, &[(SCHEMA_ASSET, &data, &url, None)])]),
+        );
+        let info = checker.check_all_updates().unwrap()["schema"].clone();
+        let zip_path = checker.cache_dir.join(&info.file_name);
+        let _ = fs::remove_file(&zip_path);
+        assert!(checker.download_file(&info.url, &zip_path, info.file_size, None, None));
+        let user = &fixture.paths.user;
+        assert!(checker.install_zip("schema", &info, &zip_path, user));
+        checker
+            .record_result("schema", &info, vec![user.clone()], true)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_removed_upstream_files_cleaned() {
+        let fixture = Fixture::new("removed_upstream");
+        let checker = fixture.checker();
+        let user = &fixture.paths.user;
+        let apply = |tag: &str, names: &[&str], seed: u8| {
+            apply_schema(&fixture, &checker, tag, names, seed)
+        };
+
+        apply(
+            "v1.0.0",
+            &["a.schema.yaml", "lua/old.lua", "dicts/old.dict.yaml"],
+            1,
+        );
+        fs::write(user.join("custom.yaml"), "mine").unwrap();
+        fs::write(user.join("dicts/old.dict.yaml"), "edited").unwrap();
+
+        apply("v1.1.0", &["a.schema.yaml"], 2);
+        // 未修改的文件被隔离，空目录一并删除
+        assert!(!user.join("lua").exists());
+        let quarantine = fixture.paths.state.join("quarantine/schema");
+        let moved = staging::list_files(&quarantine).unwrap();
+        assert_eq!(moved.len(), 1);
+        assert!(moved[0].ends_with("lua/old.lua"));
+        // 用户修改过的文件和清单外的文件保持不动
+        assert_eq!(
+            fs::read_to_string(user.join("dicts/old.dict.yaml")).unwrap(),
+            "edited"
+        );
+        assert_eq!(
+            fs::read_to_string(user.join("custom.yaml")).unwrap(),
+            "mine"
+        );
+
+        // 回滚时恢复被移除的文件和旧清单
+        assert!(checker.rollback(Some("schema"), None));
+        assert_eq!(
+            fs::read(user.join("lua/old.lua")).unwrap(),
+            fake_content("lua/old.lua", 1)
+        );
+        let state = InstallState::load(&fixture.paths.state.join(STATE_FILENAME));
+        assert_eq!(state.manifest("schema").files.len(), 3);
+    }
+
+    #[test]
+    fn test_user_modifications_preserved() {
+        let mut fixture = Fixture::new("conflicts");
+        let user = fixture.paths.user.clone();
+        let names = ["custom_phrase.txt", "a.schema.yaml"];
+        let checker = fixture.checker();
+
+        apply_schema(&fixture, &checker, "v1.0.0", &names, 1);
+        fs::write(user.join("custom_phrase.txt"), "mine").unwrap();
+        // 首次安装后用户自己创建、之后上游也开始提供的文件
+        fs::write(user.join("extra.txt"), "mine").unwrap();
+
+        // 默认保留用户的版本，新版本写为 .new
+        apply_schema(
+            &fixture,
+            &checker,
+            "v1.1.0",
+            &["custom_phrase.txt", "a.schema.yaml", "extra.txt"],
+            2,
+        );
+        assert_eq!(
+            fs::read_to_string(user.join("custom_phrase.txt")).unwrap(),
+            "mine"
+        );
+        assert_eq!(
+            fs::read(user.join("custom_phrase.txt.new")).unwrap(),
+            fake_content("custom_phrase.txt", 2)
+        );
+        assert_eq!(fs::read_to_string(user.join("extra.txt")).unwrap(), "mine");
+        assert_eq!(
+            fs::read(user.join("a.schema.yaml")).unwrap(),
+            fake_content("a.schema.yaml", 2)
+        );
+        assert_eq!(checker.conflicts().len(), 2);
+
+        // 备份用户的版本后覆盖
+        fixture.config.install.conflicts = ConflictPolicy::Backup;
+        let checker = fixture.checker();
+        apply_schema(&fixture, &checker, "v1.2.0", &names, 3);
+        assert_eq!(
+            fs::read(user.join("custom_phrase.txt")).unwrap(),
+            fake_content("custom_phrase.txt", 3)
+        );
+        let conflicts = checker.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(fs::read_to_string(&conflicts[0].saved_as).unwrap(), "mine");
+
+        // 未修改的文件直接更新，不再算作冲突
+        let checker = fixture.checker();
+        apply_schema(&fixture, &checker, "v1.3.0", &names, 4);
+        assert!(checker.conflicts().is_empty());
+    }
+
+    #[test]
+    fn test_conflicts_without_manifest() {
+        let fixture = Fixture::new("conflicts_no_manifest");
+        let user = fixture.paths.user.clone();
+        let names = ["custom_phrase.txt", "wanxiang.custom.yaml", "a.schema.yaml"];
+
+        // 升级前安装的文件没有清单，自定义文件仍按冲突处理
+        for name in names {
+            fs::write(user.join(name), "mine").unwrap();
         }
+        let checker = fixture.checker();
+        apply_schema(&fixture, &checker, "v1.0.0", &names, 1);
+
+        for name in ["custom_phrase.txt", "wanxiang.custom.yaml"] {
+            assert_eq!(fs::read_to_string(user.join(name)).unwrap(), "mine");
+            assert_eq!(
+                fs::read(user.join(format!("{}.new", name))).unwrap(),
+                fake_content(name, 1)
+            );
+        }
+        assert_eq!(
+            fs::read(user.join("a.schema.yaml")).unwrap(),
+            fake_content("a.schema.yaml", 1)
+        );
+        assert_eq!(checker.conflicts().len(), 2);
+    }
+
+    #[test]
+    fn test_filtered_files_left_alone() {
+        let mut fixture = Fixture::new("filters");
+        let user = fixture.paths.user.clone();
+        let names = ["default.yaml", "a.schema.yaml", "lua/a.lua", "lua/b.lua"];
+
+        let checker = fixture.checker();
+        apply_schema(&fixture, &checker, "v1.0.0", &names, 1);
+
+        fixture.config.filters.insert(
+            "schema".to_string(),
+            FilterConfig {
+                include: Vec::new(),
+                exclude: vec!["default.yaml".to_string(), "lua/b.lua".to_string()],
+            },
+        );
+        let checker = fixture.checker();
+        apply_schema(&fixture, &checker, "v1.1.0", &names, 2);
+
+        // 排除的文件既不更新也不被当作上游删除的文件清理
+        assert_eq!(
+            fs::read(user.join("default.yaml")).unwrap(),
+            fake_content("default.yaml", 1)
+        );
+        assert_eq!(
+            fs::read(user.join("lua/b.lua")).unwrap(),
+            fake_content("lua/b.lua", 1)
+        );
+        assert_eq!(
+            fs::read(user.join("lua/a.lua")).unwrap(),
+            fake_content("lua/a.lua", 2)
+        );
+        assert!(!fixture.paths.state.join("quarantine").exists());
+
+        let state = InstallState::load(&fixture.paths.state.join(STATE_FILENAME));
+        let manifest = state.manifest("schema");
+        assert!(manifest.files.contains_key("lua/a.lua"));
+        assert!(!manifest.files.contains_key("default.yaml"));
     }
 
-    /// 验证 ZIP 文件完整性（基本格式检查）
-    fn verify_zip_integrity(&self, file_path: &PathBuf) -> bool {
-        use std::fs::File;
-        use std::io::Read;
+    #[test]
+    fn test_pinned_schema_downgrade() {
+        let mut fixture = Fixture::new("pins");
+        let (old, new) = (fake_zip(1), fake_zip(2));
+        let old_url = fixture.add_asset(SCHEMA_REPO, "v1.0.0", SCHEMA_ASSET, &old);
+        let new_url = fixture.add_asset(SCHEMA_REPO, "v1.1.0", SCHEMA_ASSET, &new);
+        fixture.set_releases(
+            SCHEMA_REPO,
+            serde_json::json!([
+                release_json("v1.1.0", &[(SCHEMA_ASSET, &new, &new_url, None)]),
+                release_json("v1.0.0", &[(SCHEMA_ASSET, &old, &old_url, None)]),
+            ]),
+        );
 
-        // 检查文件是否以 ZIP 魔法字节开头
-        if let Ok(mut file) = File::open(file_path) {
-            let mut buffer = [0; 4];
-            if file.read_exact(&mut buffer).is_ok() {
-                // ZIP 文件的魔法字节：PK\x03\x04 或 PK\x05\x06 或 PK\x07\x08
-                return buffer[0] == 0x50
-                    && buffer[1] == 0x4B
-                    && (buffer[2] == 0x03 || buffer[2] == 0x05 || buffer[2] == 0x07);
+        let checker = fixture.checker();
+        let latest = checker.check_all_updates().unwrap()["schema"].clone();
+        assert_eq!(latest.tag, "v1.1.0");
+        install(&checker, "schema", &latest);
+
+        // 固定到旧版本时视为降级
+        fixture.config.pins.schema_version = "1.0".to_string();
+        let checker = fixture.checker();
+        let pinned = checker.check_all_updates().unwrap()["schema"].clone();
+        assert_eq!(pinned.tag, "v1.0.0");
+        assert!(pinned.pinned);
+        install(&checker, "schema", &pinned);
+        assert!(!checker.check_all_updates().unwrap().contains_key("schema"));
+
+        // 固定的版本不存在时不更新
+        fixture.config.pins.schema_version = "v0.9.0".to_string();
+        assert!(!fixture
+            .checker()
+            .check_all_updates()
+            .unwrap()
+            .contains_key("schema"));
+    }
+
+    #[test]
+    fn test_dict_pin_requires_matching_asset() {
+        let mut fixture = Fixture::new("dict_pin");
+        let dict_asset = "base-dicts.zip";
+        let data = fake_zip(1);
+        let url = fixture.add_asset(SCHEMA_REPO, "dict-nightly", dict_asset, &data);
+        fixture.set_release_by_tag(
+            SCHEMA_REPO,
+            "dict-nightly",
+            release_json("dict-nightly", &[(dict_asset, &data, &url, None)]),
+        );
+        let current = fixture.checker().check_all_updates().unwrap()["dict"].clone();
+
+        fixture.config.pins.dict_asset_id = Some(current.asset_id + 1);
+        assert!(!fixture
+            .checker()
+            .check_all_updates()
+            .unwrap()
+            .contains_key("dict"));
+
+        fixture.config.pins.dict_asset_id = Some(current.asset_id);
+        fixture.config.pins.dict_date = current.asset_updated_at[..10].to_string();
+        let updates = fixture.checker().check_all_updates().unwrap();
+        assert!(updates["dict"].pinned);
+    }
+
+    #[test]
+    fn test_schema_channels() {
+        let mut fixture = Fixture::new("channels");
+        let release = |tag: &str, seed: u8| {
+            let data = fake_zip(seed);
+            let url = fixture.add_asset(SCHEMA_REPO, tag, SCHEMA_ASSET, &data);
+            release_json(tag, &[(SCHEMA_ASSET, &data, &url, None)])
+        };
+        let mut draft = release("v1.3.0", 4);
+        draft["draft"] = true.into();
+        draft["published_at"] = serde_json::Value::Null;
+        let mut flagged = release("v1.2.0", 3);
+        flagged["prerelease"] = true.into();
+        let releases = serde_json::json!([
+            draft,
+            flagged,
+            release("v1.1.0-beta.1", 2),
+            release("v1.0.0", 1),
+        ]);
+        fixture.set_releases(SCHEMA_REPO, releases);
+        fixture.set_release_by_tag(SCHEMA_REPO, "nightly", release("nightly", 5));
+
+        let schema_tag = |fixture: &Fixture| {
+            fixture.checker().check_all_updates().unwrap()["schema"]
+                .tag
+                .clone()
+        };
+        assert_eq!(schema_tag(&fixture), "v1.0.0");
+        fixture.config.channels.schema = Channel::Beta;
+        assert_eq!(schema_tag(&fixture), "v1.3.0");
+        fixture.config.channels.schema = Channel::Nightly;
+        assert_eq!(schema_tag(&fixture), "nightly");
+    }
+
+    #[test]
+    fn test_fixed_tag_reupload_detected() {
+        let fixture = Fixture::new("fixed_tag");
+        let dict_asset = "base-dicts.zip";
+        let publish = |seed: u8| {
+            let data = fake_zip(seed);
+            let url = fixture.add_asset(SCHEMA_REPO, "dict-nightly", dict_asset, &data);
+            fixture.set_release_by_tag(
+                SCHEMA_REPO,
+                "dict-nightly",
+                release_json("dict-nightly", &[(dict_asset, &data, &url, None)]),
+            );
+        };
+        publish(1);
+
+        let checker = fixture.checker();
+        let updates = checker.check_all_updates().unwrap();
+        install(
+            &checker,
+            "dict",
+            updates.get("dict").expect("首次运行应发现词库"),
+        );
+        assert!(!checker.check_all_updates().unwrap().contains_key("dict"));
+
+        // 标签不变，但资产被重新上传
+        publish(2);
+        let updates = checker.check_all_updates().unwrap();
+        assert_eq!(
+            updates.get("dict").map(|i| i.tag.as_str()),
+            Some("dict-nightly")
+        );
+    }
+
+    #[test]
+    fn test_missing_asset_and_release() {
+        let fixture = Fixture::new("missing_asset");
+        // 词库 Release 中没有匹配的资产，模型 Release 不存在 (404)
+        fixture.set_release_by_tag(
+            SCHEMA_REPO,
+            "dict-nightly",
+            release_json(
+                "dict-nightly",
+                &[(
+                    "readme.txt",
+                    b"hello",
+                    "https://example.com/readme.txt",
+                    None,
+                )],
+            ),
+        );
+
+        // 程序自身的最新版本低于当前版本
+        let self_asset = "rime_wanxiang_updater-linux";
+        fixture.set_latest_release(
+            "Mikachu2333/rime_wanxiang_updater",
+            release_json(
+                "v0.0.1",
+                &[(self_asset, b"old", "https://example.com/old", None)],
+            ),
+        );
+
+        let updates = fixture.checker().check_all_updates().unwrap();
+        assert!(!updates.contains_key("self"));
+        assert!(!updates.contains_key("dict"));
+        assert!(!updates.contains_key("model"));
+        assert!(!updates.contains_key("schema"));
+    }
+
+    #[test]
+    fn test_bad_hash_rejected() {
+        let fixture = Fixture::new("bad_hash");
+        let data = fake_zip(3);
+        let url = fixture.add_asset(SCHEMA_REPO, "v1.0.0", SCHEMA_ASSET, &data);
+
+        let checker = fixture.checker();
+        let save_path = checker.cache_dir.join(SCHEMA_ASSET);
+        assert!(!checker.download_file(&url, &save_path, 0, Some(&"0".repeat(64)), None));
+    }
+
+    #[test]
+    fn test_auto_mirror_uses_probe_results() {
+        let mut fixture = Fixture::new("auto_mirror");
+        fixture.config.mirrors = vec!["auto".to_string(), "m1.example".to_string()];
+        let config = fixture.config.clone();
+        fixture.add_asset(
+            &config.model_repo,
+            &config.model_tag,
+            &config.model_file_name,
+            &fake_content("model", 1),
+        );
+        let data = fake_zip(3);
+        let url = fixture.add_asset(SCHEMA_REPO, "v1.0.0", SCHEMA_ASSET, &data);
+
+        // 未过期的测速结果直接使用
+        let cache_path = fixture.paths.cache.join(mirror::PROBE_CACHE_FILENAME);
+        fs::create_dir_all(&fixture.paths.cache).unwrap();
+        let mut cache = ProbeCache {
+            probed_at: unix_now(),
+            results: vec![mirror::ProbeResult {
+                name: "m1.example".to_string(),
+                latency_ms: Some(10),
+                throughput: 1000,
+            }],
+        };
+        cache.save(&cache_path).unwrap();
+        let checker = fixture.checker();
+        assert!(checker.download_file(&url, &checker.cache_dir.join(SCHEMA_ASSET), 0, None, None));
+        assert_eq!(
+            checker.download_sources(),
+            vec![(SCHEMA_ASSET.to_string(), "m1.example".to_string())]
+        );
+
+        // 过期后重新测速，所有候选都会被测到
+        cache.probed_at = 0;
+        cache.save(&cache_path).unwrap();
+        let checker = fixture.checker();
+        fs::remove_file(checker.cache_dir.join(SCHEMA_ASSET)).unwrap();
+        assert!(checker.download_file(&url, &checker.cache_dir.join(SCHEMA_ASSET), 0, None, None));
+        let probed = ProbeCache::load(&cache_path, Duration::from_secs(60)).unwrap();
+        assert_eq!(
+            probed.results.len(),
+            mirror::candidates(&config.mirrors).len() + 1
+        );
+        assert!(probed.results.iter().all(|r| r.is_healthy()));
+    }
+
+    /// 镜像地址一律返回验证页面，直连地址交给本地目录
+    struct CaptchaMirror(LocalTransport);
+
+    impl Transport for CaptchaMirror {
+        fn name(&self) -> &'static str {
+            "captcha"
+        }
+
+        fn get(
+            &self,
+            url: &str,
+            headers: &[(&str, &str)],
+        ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+            self.0.get(url, headers)
+        }
+
+        fn fetch_prefix(
+            &self,
+            url: &str,
+            headers: &[(&str, &str)],
+            limit: u64,
+        ) -> Result<(u16, u64), Box<dyn std::error::Error>> {
+            self.0.fetch_prefix(url, headers, limit)
+        }
+
+        fn download(
+            &self,
+            url: &str,
+            save_path: &Path,
+            headers: &[(&str, &str)],
+        ) -> Result<DownloadResult, Box<dyn std::error::Error>> {
+            if url.starts_with("https://github.com/") {
+                return self.0.download(url, save_path, headers);
             }
+            let page = "<!DOCTYPE html><html><body>请完成验证</body></html>".repeat(40);
+            fs::write(save_path, &page)?;
+            Ok(DownloadResult {
+                status: 200,
+                content_length: Some(page.len() as u64),
+                content_type: Some("text/html; charset=utf-8".to_string()),
+                etag: None,
+                bytes_written: page.len() as u64,
+            })
         }
-        false
     }
 
-    pub fn extract_zip(&self, zip_path: &PathBuf, extract_path: &PathBuf) -> bool {
-        self.file_ops.extract_zip(zip_path, extract_path)
+    #[test]
+    fn test_mirror_error_page_falls_back() {
+        let mut fixture = Fixture::new("captcha_mirror");
+        fixture.config.mirrors = vec!["gh-proxy.com".to_string()];
+        fixture.config.download_retries = 1;
+        let data = fake_zip(5);
+        let url = fixture.add_asset(SCHEMA_REPO, "v1.0.0", SCHEMA_ASSET, &data);
+
+        let checker = fixture.checker_with(Rc::new(CaptchaMirror(LocalTransport::new(
+            &fixture.releases_dir(),
+        ))));
+        let save_path = checker.cache_dir.join(SCHEMA_ASSET);
+        assert!(checker.download_file(&url, &save_path, data.len() as u64, None, None));
+        assert_eq!(fs::read(&save_path).unwrap(), data);
+        assert_eq!(
+            checker.download_sources(),
+            vec![(SCHEMA_ASSET.to_string(), mirror::DIRECT.to_string())]
+        );
+
+        // 缓存中大小不符的文件重新下载
+        fs::write(&save_path, &data[..data.len() / 2]).unwrap();
+        assert!(checker.download_file(&url, &save_path, data.len() as u64, None, None));
+        assert_eq!(fs::read(&save_path).unwrap(), data);
     }
 
-    pub fn deploy_weasel(&self) -> bool {
-        self.weasel_mgr.deploy()
+    #[test]
+    fn test_update_flow_over_local_server() {
+        let fixture = Fixture::new("local_server");
+        publish_schema(&fixture, "v2.0.0", 4);
+
+        let server = LocalServer::start(&fixture.releases_dir()).unwrap();
+        let checker = fixture.checker_with(Rc::new(server.transport()));
+        let updates = checker.check_all_updates().unwrap();
+        let schema = updates.get("schema").expect("应通过本地服务发现方案更新");
+        assert_eq!(schema.tag, "v2.0.0");
+        install(&checker, "schema", schema);
+        assert_eq!(
+            fs::read(checker.cache_dir.join(SCHEMA_ASSET)).unwrap(),
+            fake_zip(4)
+        );
     }
 }
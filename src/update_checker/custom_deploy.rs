@@ -0,0 +1,221 @@
+use std::{
+    io::{BufRead, BufReader, Read},
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::mpsc::{self, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::frontend::Frontend;
+use crate::types::DeployConfig;
+use crate::{platform, run_log};
+
+/// 命令结束后等待输出转发完成的最长时间
+///
+/// 命令启动的后台进程可能继承并一直占用输出管道，超时后不再等待
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 使用自定义命令部署的前端
+///
+/// 目录定位等操作仍交给实际的前端，只替换部署方式
+pub struct CustomDeployFrontend {
+    inner: Box<dyn Frontend>,
+    config: DeployConfig,
+}
+
+impl CustomDeployFrontend {
+    pub fn new(inner: Box<dyn Frontend>, config: DeployConfig) -> Self {
+        Self { inner, config }
+    }
+
+    /// 执行自定义部署命令，输出逐行转发到运行日志
+    fn run_command(&self) -> bool {
+        println!(
+            "正在执行自定义部署命令: {} {}",
+            self.config.command,
+            self.config.args.join(" ")
+        );
+
+        let mut command = Command::new(&self.config.command);
+        command
+            .args(&self.config.args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        // 超时时连同命令启动的进程一起结束
+        platform::new_process_group(&mut command);
+        if let Some(dir) = &self.config.working_dir {
+            command.current_dir(dir);
+        }
+
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                eprintln!("❌ 执行部署命令失败: {}", e);
+                return false;
+            }
+        };
+
+        let (done_tx, done_rx) = mpsc::channel();
+        let mut forwarders = 0;
+        if let Some(out) = child.stdout.take() {
+            forward_output(out, false, done_tx.clone());
+            forwarders += 1;
+        }
+        if let Some(err) = child.stderr.take() {
+            forward_output(err, true, done_tx);
+            forwarders += 1;
+        }
+
+        let started = Instant::now();
+        let timeout = Duration::from_secs(self.config.timeout);
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break Some(status),
+                Ok(None) => {
+                    if self.config.timeout > 0 && started.elapsed() >= timeout {
+                        eprintln!("❌ 部署命令超时 ({} 秒)，正在终止", self.config.timeout);
+                        platform::kill_process_tree(&mut child);
+                        break None;
+                    }
+                    thread::sleep(Duration::from_millis(100));
+                }
+                Err(e) => {
+                    eprintln!("❌ 等待部署命令失败: {}", e);
+                    break None;
+                }
+            }
+        };
+
+        let deadline = Instant::now() + OUTPUT_DRAIN_TIMEOUT;
+        for _ in 0..forwarders {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if done_rx.recv_timeout(remaining).is_err() {
+                println!("⚠️ 部署命令启动的进程仍在占用输出，不再等待");
+                break;
+            }
+        }
+
+        run_log::record(&format!("自定义部署命令结束: {:?}", status));
+
+        match status.map(|s| s.code()) {
+            Some(Some(code)) if code == self.config.expected_exit_code => {
+                println!("✅ 自定义部署命令执行成功");
+                true
+            }
+            Some(Some(code)) => {
+                eprintln!(
+                    "❌ 自定义部署命令退出码为 {}，期望 {}",
+                    code, self.config.expected_exit_code
+                );
+                false
+            }
+            Some(None) => {
+                eprintln!("❌ 自定义部署命令被信号终止");
+                false
+            }
+            None => false,
+        }
+    }
+}
+
+/// 在后台线程中逐行转发子进程输出，读完后通过 done 通知
+fn forward_output<R: Read + Send + 'static>(reader: R, is_stderr: bool, done: Sender<()>) {
+    thread::spawn(move || {
+        for line in BufReader::new(reader).lines().map_while(Result::ok) {
+            if is_stderr {
+                eprintln!("  [deploy:stderr] {}", line);
+                run_log::record(&format!("[deploy:stderr] {}", line));
+            } else {
+                println!("  [deploy:stdout] {}", line);
+                run_log::record(&format!("[deploy:stdout] {}", line));
+            }
+        }
+        let _ = done.send(());
+    });
+}
+
+impl Frontend for CustomDeployFrontend {
+    fn name(&self) -> &'static str {
+        "自定义部署命令"
+    }
+
+    fn root_dir(&self) -> PathBuf {
+        self.inner.root_dir()
+    }
+
+    fn user_dir(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        self.inner.user_dir()
+    }
+
+    fn shared_dir(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        self.inner.shared_dir()
+    }
+
+    fn deploy(&self) -> bool {
+        if self.config.is_disabled() {
+            println!("⚠️ 已配置为不自动部署，请手动重新部署");
+            return true;
+        }
+        self.run_command()
+    }
+
+    fn stop(&self) {
+        self.inner.stop();
+    }
+
+    fn start(&self) {
+        self.inner.start();
+    }
+
+    fn bundled_tool(&self, name: &str) -> PathBuf {
+        self.inner.bundled_tool(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::update_checker::frontend::{self, FrontendKind};
+
+    fn frontend_with(command: &str, args: &[&str], timeout: u64) -> CustomDeployFrontend {
+        CustomDeployFrontend::new(
+            frontend::create(FrontendKind::Fcitx5, &PathBuf::new()),
+            DeployConfig {
+                command: command.to_string(),
+                args: args.iter().map(|a| a.to_string()).collect(),
+                working_dir: None,
+                timeout,
+                expected_exit_code: 0,
+            },
+        )
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_custom_deploy_exit_code() {
+        assert!(frontend_with("sh", &["-c", "echo deployed"], 0).deploy());
+        assert!(!frontend_with("sh", &["-c", "exit 3"], 0).deploy());
+        assert!(!frontend_with("sh", &["-c", "exec sleep 5"], 1).deploy());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_custom_deploy_background_process() {
+        // 后台进程继承输出管道时，超时仍然生效
+        let started = Instant::now();
+        assert!(!frontend_with("sh", &["-c", "sleep 30 & sleep 5"], 1).deploy());
+        assert!(started.elapsed() < Duration::from_secs(4));
+
+        // 命令正常结束后不等待仍在运行的后台进程
+        let started = Instant::now();
+        assert!(frontend_with("sh", &["-c", "sleep 3 & echo deployed"], 0).deploy());
+        assert!(started.elapsed() < Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_custom_deploy_none() {
+        assert!(frontend_with("none", &[], 0).deploy());
+    }
+}
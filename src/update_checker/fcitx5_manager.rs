@@ -0,0 +1,146 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::Duration,
+};
+
+use super::frontend::{self, find_in_path, Frontend};
+
+/// fcitx5-rime 前端
+pub struct Fcitx5Manager {
+    home: PathBuf,
+    /// 手动指定的程序所在目录
+    root: Option<PathBuf>,
+}
+
+impl Fcitx5Manager {
+    pub fn new() -> Self {
+        Self {
+            home: std::env::var_os("HOME")
+                .map(PathBuf::from)
+                .unwrap_or_default(),
+            root: None,
+        }
+    }
+
+    /// 使用指定的程序所在目录（如 /opt/fcitx5/bin）
+    pub fn with_root(root: &Path) -> Self {
+        Self {
+            root: Some(root.to_path_buf()),
+            ..Self::new()
+        }
+    }
+
+    /// 通过 fcitx5 的 D-Bus 控制器触发 rime 重新部署
+    fn deploy_via_dbus(&self) -> bool {
+        let output = Command::new("dbus-send")
+            .args([
+                "--session",
+                "--print-reply",
+                "--dest=org.fcitx.Fcitx5",
+                "/controller",
+                "org.fcitx.Fcitx.Controller1.SetConfig",
+                "string:fcitx://config/addon/rime/deploy",
+                "variant:string:",
+            ])
+            .output();
+
+        match output {
+            Ok(output) if output.status.success() => true,
+            Ok(output) => {
+                eprintln!(
+                    "⚠️ D-Bus 部署请求失败: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+                false
+            }
+            Err(e) => {
+                eprintln!("⚠️ 无法调用 dbus-send: {}", e);
+                false
+            }
+        }
+    }
+
+    /// 通过 fcitx5-remote 重新加载
+    fn deploy_via_remote(&self) -> bool {
+        match Command::new(self.bundled_tool("fcitx5-remote"))
+            .arg("-r")
+            .status()
+        {
+            Ok(status) if status.success() => true,
+            Ok(status) => {
+                eprintln!("❌ fcitx5-remote 执行失败，状态码: {}", status);
+                false
+            }
+            Err(e) => {
+                eprintln!("❌ 无法调用 fcitx5-remote: {}", e);
+                false
+            }
+        }
+    }
+}
+
+impl Default for Fcitx5Manager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Frontend for Fcitx5Manager {
+    fn name(&self) -> &'static str {
+        "fcitx5-rime"
+    }
+
+    fn root_dir(&self) -> PathBuf {
+        if let Some(root) = &self.root {
+            return root.clone();
+        }
+        find_in_path("fcitx5")
+            .and_then(|p| p.parent().map(|p| p.to_path_buf()))
+            .unwrap_or_else(|| PathBuf::from("/usr/bin"))
+    }
+
+    fn user_dir(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        if self.home.as_os_str().is_empty() {
+            return Err("未设置 HOME 环境变量，无法定位 fcitx5-rime 用户目录".into());
+        }
+        Ok(self.home.join(".local/share/fcitx5/rime"))
+    }
+
+    fn shared_dir(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        frontend::find_shared_dir(self.root.as_deref())
+    }
+
+    /// 部署 fcitx5-rime，优先使用 D-Bus，失败时回退到 fcitx5-remote
+    fn deploy(&self) -> bool {
+        println!("正在部署 fcitx5-rime...");
+
+        if self.deploy_via_dbus() || self.deploy_via_remote() {
+            println!("✅ fcitx5-rime 部署成功");
+            true
+        } else {
+            eprintln!("❌ fcitx5-rime 部署失败");
+            false
+        }
+    }
+
+    fn stop(&self) {
+        println!("正在停止 fcitx5...");
+        let _ = Command::new(self.bundled_tool("fcitx5-remote"))
+            .arg("-e")
+            .status();
+        thread::sleep(Duration::from_secs(1));
+    }
+
+    fn start(&self) {
+        println!("正在启动 fcitx5...");
+        let _ = Command::new(self.bundled_tool("fcitx5"))
+            .args(["-d", "--replace"])
+            .spawn();
+    }
+
+    fn bundled_tool(&self, name: &str) -> PathBuf {
+        frontend::find_tool(self.root.as_deref(), name)
+    }
+}
@@ -1,125 +1,772 @@
-use std::{path::PathBuf, process::Command};
+use super::{mirror::DownloadSource, path_filter::PathFilter, transport::Transport};
+use crate::file_checker::{self, FileFormat};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use zip::ZipArchive;
 
 pub struct FileOperations {
-    zip_path: PathBuf,
+    limits: ExtractLimits,
+    retry: RetryPolicy,
+}
+
+/// 单次下载失败的原因
+enum DownloadFailure {
+    /// 连接中断、服务器繁忙等，可以重试
+    Transient(String),
+    /// 资源不存在、返回错误页面等，重试无意义，直接换下一个下载源
+    Permanent(String),
+}
+
+/// 下载的文件应满足的条件，不满足时视为该下载源的失败
+#[derive(Debug, Clone, Default)]
+pub struct ExpectedContent {
+    /// 资产大小，0 表示未知
+    pub size: u64,
+    pub format: FileFormat,
+    pub sha3_256: Option<String>,
 }
 
 impl FileOperations {
-    pub fn new(zip_path: &PathBuf) -> Self {
+    pub fn new(retry: RetryPolicy) -> Self {
         Self {
-            zip_path: zip_path.clone(),
+            limits: ExtractLimits::default(),
+            retry,
         }
     }
 
-    /// 下载文件
+    /// 依次从各下载源下载文件，返回成功的下载源名称
+    ///
+    /// 暂时性错误按指数退避重试，重试用尽或遇到其他错误时换下一个下载源；
+    /// 内容不符合 expected 的下载源同样跳过
     pub fn download_file(
         &self,
-        curl_path: &PathBuf,
-        url: &str,
-        save_path: &PathBuf,
+        transport: &dyn Transport,
+        sources: &[DownloadSource],
+        save_path: &Path,
+        expected: &ExpectedContent,
         github_cookie: Option<String>,
-    ) -> bool {
-        println!("正在下载: {}", url);
-
-        // 如果文件已存在，先删除
-        if save_path.exists() {
-            if let Err(e) = std::fs::remove_file(save_path) {
-                eprintln!("❌ 无法删除旧文件: {}", e);
-                return false;
+    ) -> Option<String> {
+        for (i, source) in sources.iter().enumerate() {
+            if i > 0 {
+                println!("🔁 改用下一个下载源: {}", source.name);
+            }
+            for attempt in 1..=self.retry.attempts.max(1) {
+                let error = match self.try_download(
+                    transport,
+                    &source.url,
+                    save_path,
+                    expected,
+                    &github_cookie,
+                ) {
+                    Ok(()) => return Some(source.name.clone()),
+                    Err(DownloadFailure::Transient(e)) if attempt < self.retry.attempts => e,
+                    Err(DownloadFailure::Transient(e) | DownloadFailure::Permanent(e)) => {
+                        eprintln!("❌ {} 下载失败: {}", source.name, e);
+                        break;
+                    }
+                };
+                let delay = self.retry.delay(attempt);
+                eprintln!(
+                    "⚠️ {} 下载失败: {}，{:.1} 秒后重试 ({}/{})",
+                    source.name,
+                    error,
+                    delay.as_secs_f64(),
+                    attempt,
+                    self.retry.attempts
+                );
+                std::thread::sleep(delay);
             }
         }
+        eprintln!("❌ 所有下载源均失败");
+        None
+    }
 
-        // 使用 spawn 和 wait 来实现实时进度显示
-        let mut command = Command::new(curl_path);
-        command
-            .args(&[
-                "-C",
-                "-",              // 断点续传
-                "-L",             // 跟随重定向
-                "--progress-bar", // 显示进度条
-                "--fail",         // 在HTTP错误时失败
-                "--connect-timeout",
-                "30", // 连接超时
-                "--max-time",
-                "1800", // 最大下载时间(30分钟)
-                "-o",   // 输出文件
-            ])
-            .arg(save_path)
-            .arg(url);
-
-        // 如果提供了cookie，添加cookie参数
+    /// 从单个地址下载一次
+    ///
+    /// 先下载到 `<文件名>.part`，旁边的 `.part.json` 记录地址、ETag 和资产大小，
+    /// 中断后下次尝试时用 Range 请求继续下载；校验通过后才重命名到 save_path
+    fn try_download(
+        &self,
+        transport: &dyn Transport,
+        url: &str,
+        save_path: &Path,
+        expected: &ExpectedContent,
+        github_cookie: &Option<String>,
+    ) -> Result<(), DownloadFailure> {
+        use DownloadFailure::{Permanent, Transient};
+        println!("正在下载: {}", url);
+
+        let (part_path, info_path) = part_paths(save_path);
+        let previous = PartInfo::load(&info_path);
+        let offset = resume_offset(&part_path, previous.as_ref(), url, expected.size);
+        let mut info = PartInfo {
+            url: url.to_string(),
+            etag: previous
+                .filter(|p| offset > 0 && p.url == url)
+                .and_then(|p| p.etag),
+            size: expected.size,
+        };
+
+        // 如果提供了cookie，添加cookie请求头
+        let mut headers = Vec::new();
         if let Some(cookie) = github_cookie {
-            command.arg("-H").arg(format!("Cookie: {}", cookie));
+            headers.push(("Cookie", cookie.as_str()));
         }
-
-        let mut child = match command.spawn() {
-            Ok(child) => child,
-            Err(e) => {
-                eprintln!("❌ 执行curl命令失败: {}", e);
-                return false;
+        let range = format!("bytes={}-", offset);
+        if offset > 0 {
+            println!("⏯️ 从 {:.1} MB 处继续下载", offset as f64 / 1048576.0);
+            headers.push(("Range", range.as_str()));
+            // 文件已变化时服务器返回完整内容
+            if let Some(etag) = &info.etag {
+                headers.push(("If-Range", etag.as_str()));
             }
+        }
+        info.save(&info_path);
+
+        let result = match transport.download(url, &part_path, &headers) {
+            Ok(result) => result,
+            Err(e) => return Err(Transient(format!("{}: {}", transport.name(), e))),
         };
+        if result.etag.is_some() && result.etag != info.etag {
+            info.etag = result.etag.clone();
+            info.save(&info_path);
+        }
 
-        // 等待下载完成
-        let result = match child.wait() {
-            Ok(status) => status,
-            Err(e) => {
-                eprintln!("❌ 等待curl进程失败: {}", e);
-                return false;
+        if result.status == 416 {
+            discard_part(&part_path, &info_path);
+            return Err(Transient("续传位置无效，重新下载".to_string()));
+        }
+        if !result.is_success() {
+            let message = format!("HTTP 状态码 {}", result.status);
+            return Err(match result.status {
+                408 | 425 | 429 | 500..=599 => Transient(message),
+                _ => Permanent(message),
+            });
+        }
+
+        // 不完整的 .part 文件留到下次继续下载
+        if let Some(expected) = result.content_length {
+            if result.bytes_written != expected {
+                return Err(Transient(format!(
+                    "下载不完整: {} / {} bytes",
+                    result.bytes_written, expected
+                )));
             }
+        }
+
+        // 验证文件是否确实下载完成
+        let Ok(metadata) = fs::metadata(&part_path) else {
+            return Err(Transient("下载后文件不存在".to_string()));
         };
+        let file_size = metadata.len();
+        if let Err(e) = verify_content(&part_path, file_size, expected, &result.content_type) {
+            if matches!(e, Permanent(_)) {
+                discard_part(&part_path, &info_path);
+            }
+            return Err(e);
+        }
 
-        if result.success() {
-            // 验证文件是否确实下载完成
-            if save_path.exists() {
-                let file_size = std::fs::metadata(save_path).map(|m| m.len()).unwrap_or(0);
-
-                if file_size > 1000 {
-                    // 至少1KB，避免下载失败的小文件
-                    println!("✅ 下载完成: {:?} ({} bytes)", save_path, file_size);
-                    true
-                } else {
-                    eprintln!("❌ 下载的文件太小，可能下载失败: {} bytes", file_size);
-                    // 清理不完整的文件
-                    let _ = std::fs::remove_file(save_path);
-                    false
-                }
-            } else {
-                eprintln!("❌ 下载后文件不存在");
-                false
+        if save_path.exists() {
+            if let Err(e) = fs::remove_file(save_path) {
+                return Err(Permanent(format!("无法删除旧文件: {}", e)));
             }
-        } else {
-            eprintln!("❌ 下载失败，curl退出码: {}", result);
-            false
         }
+        if let Err(e) = fs::rename(&part_path, save_path) {
+            return Err(Permanent(format!("无法移动下载的文件: {}", e)));
+        }
+        let _ = fs::remove_file(&info_path);
+
+        println!(
+            "✅ 下载完成: {:?} ({} bytes, {})",
+            save_path,
+            file_size,
+            result.content_type.as_deref().unwrap_or("未知类型")
+        );
+        Ok(())
     }
 
-    /// 解压ZIP文件
-    pub fn extract_zip(&self, zip_path: &PathBuf, extract_path: &PathBuf) -> bool {
+    /// 解压ZIP文件，跳过 filter 不允许的文件
+    ///
+    /// 拒绝绝对路径、`..` 和符号链接条目，并限制条目数量和解压后总大小
+    pub fn extract_zip(&self, zip_path: &Path, extract_path: &Path, filter: &PathFilter) -> bool {
         println!("正在解压文件...");
 
-        let output = Command::new(&self.zip_path)
-            .args(&["x"]) // 解压命令
-            .arg(zip_path) // 源文件
-            .arg(&format!("-o{}", extract_path.display())) // 输出目录
-            .arg("-y") // 覆盖确认
-            .status();
-
-        match output {
-            Ok(status) => {
-                if status.success() {
-                    println!("✅ 解压完成: {:?}", extract_path);
-                    true
-                } else {
-                    eprintln!("❌ 解压失败，状态码: {}", status);
-                    false
+        match extract_archive(zip_path, extract_path, &self.limits, filter) {
+            Ok((count, skipped)) => {
+                println!("✅ 解压完成: {:?} ({} 个文件)", extract_path, count);
+                if !skipped.is_empty() {
+                    println!("⏭️ 按筛选规则跳过 {} 个文件:", skipped.len());
+                    for path in &skipped {
+                        println!("  {}", path.display());
+                    }
                 }
+                true
             }
             Err(e) => {
                 eprintln!("❌ 解压失败: {}", e);
-                eprintln!("   请确保7z.exe存在于小狼毫安装目录中");
                 false
             }
         }
     }
 }
+
+/// 断点续传信息，保存在 `.part` 文件旁边
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PartInfo {
+    url: String,
+    etag: Option<String>,
+    /// 资产大小，0 表示未知
+    size: u64,
+}
+
+impl PartInfo {
+    fn load(path: &Path) -> Option<Self> {
+        serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+    }
+
+    /// 写入失败只影响下次续传，不中断下载
+    fn save(&self, path: &Path) {
+        if let Ok(content) = serde_json::to_string(self) {
+            let _ = fs::write(path, content);
+        }
+    }
+}
+
+/// 下载中的文件 `<文件名>.part` 及其续传信息 `<文件名>.part.json`
+fn part_paths(save_path: &Path) -> (PathBuf, PathBuf) {
+    let name = save_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    (
+        save_path.with_file_name(format!("{}.part", name)),
+        save_path.with_file_name(format!("{}.part.json", name)),
+    )
+}
+
+/// 可以继续下载的位置，无法续传时删除残留的 `.part` 文件并返回 0
+///
+/// 资产大小已知时各镜像的内容相同，可以换下载源续传；大小未知时只在同一地址续传
+fn resume_offset(part_path: &Path, info: Option<&PartInfo>, url: &str, size: u64) -> u64 {
+    let len = fs::metadata(part_path).map(|m| m.len()).unwrap_or(0);
+    let resumable = info.is_some_and(|info| {
+        info.size == size && (size > 0 || info.url == url) && (size == 0 || len < size)
+    });
+    if len > 0 && resumable {
+        return len;
+    }
+    let _ = fs::remove_file(part_path);
+    0
+}
+
+fn discard_part(part_path: &Path, info_path: &Path) {
+    let _ = fs::remove_file(part_path);
+    let _ = fs::remove_file(info_path);
+}
+
+/// 检查下载的文件：大小与资产一致、不是网页、格式正确、校验和一致
+fn verify_content(
+    save_path: &Path,
+    file_size: u64,
+    expected: &ExpectedContent,
+    content_type: &Option<String>,
+) -> Result<(), DownloadFailure> {
+    use DownloadFailure::{Permanent, Transient};
+
+    if expected.size > 0 && file_size != expected.size {
+        let message = format!("文件大小不符: {} / {} bytes", file_size, expected.size);
+        // 比资产小多半是连接中断，比资产大则是返回了其他内容
+        return Err(if file_size < expected.size {
+            Transient(message)
+        } else {
+            Permanent(message)
+        });
+    }
+    if let Some(content_type) = content_type {
+        if content_type.to_lowercase().starts_with("text/html") {
+            return Err(Permanent(format!(
+                "返回了网页而不是文件 ({})",
+                content_type
+            )));
+        }
+    }
+    file_checker::verify_format(save_path, expected.format).map_err(Permanent)?;
+    if let Some(hash) = &expected.sha3_256 {
+        match file_checker::hash_file(save_path) {
+            Ok(actual) if actual.eq_ignore_ascii_case(hash) => {}
+            Ok(actual) => {
+                return Err(Permanent(format!(
+                    "校验和不符: 期望 {}，实际 {}",
+                    hash, actual
+                )))
+            }
+            Err(e) => return Err(Transient(format!("无法计算校验和: {}", e))),
+        }
+    }
+    Ok(())
+}
+
+impl Default for FileOperations {
+    fn default() -> Self {
+        Self::new(RetryPolicy::default())
+    }
+}
+
+/// 下载重试策略
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// 每个下载源最多尝试的次数
+    pub attempts: u32,
+    /// 第一次重试前的等待时间，之后每次翻倍
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// 第 attempt 次失败后的等待时间
+    fn delay(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(1 << (attempt - 1).min(16))
+            .min(MAX_RETRY_DELAY)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            base_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+/// 单次重试等待时间的上限
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// 解压限制，防止恶意或损坏的压缩包耗尽磁盘
+#[derive(Debug, Clone)]
+pub struct ExtractLimits {
+    pub max_entries: usize,
+    pub max_total_size: u64,
+}
+
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: 50_000,
+            max_total_size: 2 * 1024 * 1024 * 1024, // 2 GB
+        }
+    }
+}
+
+fn extract_archive(
+    zip_path: &Path,
+    extract_path: &Path,
+    limits: &ExtractLimits,
+    filter: &PathFilter,
+) -> Result<(usize, Vec<PathBuf>), Box<dyn std::error::Error>> {
+    let mut archive = ZipArchive::new(File::open(zip_path)?)?;
+    let total = archive.len();
+    if total > limits.max_entries {
+        return Err(format!("条目数量过多: {} (上限 {})", total, limits.max_entries).into());
+    }
+
+    // 先检查全部条目，避免解压到一半才发现问题
+    let mut entries = Vec::with_capacity(total);
+    let mut skipped = Vec::new();
+    let mut declared_size = 0u64;
+    for i in 0..total {
+        let entry = archive.by_index(i)?;
+        if entry.is_symlink() {
+            return Err(format!("不允许符号链接: {}", entry.name()).into());
+        }
+        let relative = safe_relative_path(entry.name())
+            .ok_or_else(|| format!("不安全的路径: {}", entry.name()))?;
+        if !filter.allows(&relative) {
+            if !entry.is_dir() {
+                skipped.push(relative);
+            }
+            continue;
+        }
+        declared_size = declared_size.saturating_add(entry.size());
+        if declared_size > limits.max_total_size {
+            return Err(format!("解压后总大小超过上限 {} bytes", limits.max_total_size).into());
+        }
+        entries.push((i, relative, entry.is_dir()));
+    }
+
+    fs::create_dir_all(extract_path)?;
+    // 只统计文件，目录条目不计入
+    let count = entries.iter().filter(|(_, _, is_dir)| !is_dir).count();
+    let mut n = 0;
+    let mut written = 0u64;
+    for (i, relative, is_dir) in entries {
+        let target = extract_path.join(&relative);
+        if is_dir {
+            fs::create_dir_all(&target)?;
+            continue;
+        }
+        n += 1;
+        println!("[{}/{}] {}", n, count, relative.display());
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // 按实际解压的字节数计数，不信任条目头中声明的大小
+        let remaining = limits.max_total_size - written;
+        let mut entry = archive.by_index(i)?;
+        let mut output = File::create(&target)?;
+        let copied = io::copy(&mut (&mut entry).take(remaining + 1), &mut output)?;
+        if copied > remaining {
+            drop(output);
+            let _ = fs::remove_file(&target);
+            return Err(format!("解压后总大小超过上限 {} bytes", limits.max_total_size).into());
+        }
+        written += copied;
+    }
+
+    Ok((count, skipped))
+}
+
+/// 将压缩包中的条目名转换为安全的相对路径
+///
+/// 同时处理 `/` 和 `\` 分隔符，拒绝绝对路径、盘符和 `..`
+fn safe_relative_path(name: &str) -> Option<PathBuf> {
+    if name.starts_with(['/', '\\']) || name.contains(':') || name.contains('\0') {
+        return None;
+    }
+
+    let mut path = PathBuf::new();
+    for part in name.split(['/', '\\']) {
+        match part {
+            "" | "." => continue,
+            ".." => return None,
+            part => path.push(part),
+        }
+    }
+    (!path.as_os_str().is_empty()).then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FilterConfig;
+    use crate::update_checker::transport::{DownloadResult, HttpResponse};
+    use std::{cell::RefCell, collections::VecDeque, io::Write};
+    use zip::write::SimpleFileOptions;
+
+    /// 按顺序返回预设状态码的传输，None 表示连接错误
+    struct ScriptedTransport {
+        statuses: RefCell<VecDeque<Option<u16>>>,
+        requests: RefCell<Vec<String>>,
+    }
+
+    impl ScriptedTransport {
+        fn new(statuses: &[Option<u16>]) -> Self {
+            Self {
+                statuses: RefCell::new(statuses.iter().copied().collect()),
+                requests: RefCell::new(Vec::new()),
+            }
+        }
+    }
+
+    impl Transport for ScriptedTransport {
+        fn name(&self) -> &'static str {
+            "scripted"
+        }
+
+        fn get(
+            &self,
+            _url: &str,
+            _headers: &[(&str, &str)],
+        ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+            Err("不支持".into())
+        }
+
+        fn fetch_prefix(
+            &self,
+            _url: &str,
+            _headers: &[(&str, &str)],
+            _limit: u64,
+        ) -> Result<(u16, u64), Box<dyn std::error::Error>> {
+            Err("不支持".into())
+        }
+
+        fn download(
+            &self,
+            url: &str,
+            save_path: &Path,
+            _headers: &[(&str, &str)],
+        ) -> Result<DownloadResult, Box<dyn std::error::Error>> {
+            self.requests.borrow_mut().push(url.to_string());
+            let status = self
+                .statuses
+                .borrow_mut()
+                .pop_front()
+                .flatten()
+                .ok_or("connection reset")?;
+            let data = vec![b'x'; 2000];
+            fs::write(save_path, &data)?;
+            Ok(DownloadResult {
+                status,
+                content_length: Some(data.len() as u64),
+                content_type: None,
+                etag: None,
+                bytes_written: data.len() as u64,
+            })
+        }
+    }
+
+    /// 第一次请求写入一半后断开，之后按 Range 请求返回剩余部分
+    struct DroppingTransport {
+        data: Vec<u8>,
+        ranges: RefCell<Vec<Option<String>>>,
+    }
+
+    impl Transport for DroppingTransport {
+        fn name(&self) -> &'static str {
+            "dropping"
+        }
+
+        fn get(
+            &self,
+            _url: &str,
+            _headers: &[(&str, &str)],
+        ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+            Err("不支持".into())
+        }
+
+        fn fetch_prefix(
+            &self,
+            _url: &str,
+            _headers: &[(&str, &str)],
+            _limit: u64,
+        ) -> Result<(u16, u64), Box<dyn std::error::Error>> {
+            Err("不支持".into())
+        }
+
+        fn download(
+            &self,
+            _url: &str,
+            save_path: &Path,
+            headers: &[(&str, &str)],
+        ) -> Result<DownloadResult, Box<dyn std::error::Error>> {
+            let range = headers
+                .iter()
+                .find(|(k, _)| *k == "Range")
+                .map(|(_, v)| v.to_string());
+            let first = self.ranges.borrow().is_empty();
+            self.ranges.borrow_mut().push(range.clone());
+            if first {
+                fs::write(save_path, &self.data[..self.data.len() / 2])?;
+                return Err("connection reset".into());
+            }
+
+            let offset: usize = range
+                .as_deref()
+                .and_then(|r| r.strip_prefix("bytes=")?.strip_suffix('-')?.parse().ok())
+                .unwrap_or(0);
+            let rest = &self.data[offset..];
+            let mut file = fs::OpenOptions::new().append(true).open(save_path)?;
+            file.write_all(rest)?;
+            Ok(DownloadResult {
+                status: 206,
+                content_length: Some(rest.len() as u64),
+                content_type: None,
+                etag: Some("\"v1\"".to_string()),
+                bytes_written: rest.len() as u64,
+            })
+        }
+    }
+
+    #[test]
+    fn test_download_resumes_part_file() {
+        let dir = std::env::temp_dir().join(format!("rime_resume_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let save_path = dir.join("model.gram");
+        fs::write(&save_path, "old").unwrap();
+        let data: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        let reference = dir.join("reference");
+        fs::write(&reference, &data).unwrap();
+        let transport = DroppingTransport {
+            data: data.clone(),
+            ranges: RefCell::new(Vec::new()),
+        };
+        let ops = FileOperations::new(RetryPolicy {
+            attempts: 2,
+            base_delay: Duration::ZERO,
+        });
+        let sources = [DownloadSource {
+            name: "GitHub".to_string(),
+            mirror: "GitHub".to_string(),
+            url: "https://github.com/a/b/releases/download/v1/model.gram".to_string(),
+        }];
+        let expected = ExpectedContent {
+            size: data.len() as u64,
+            format: FileFormat::Gram,
+            sha3_256: Some(file_checker::hash_file(&reference).unwrap()),
+        };
+
+        assert!(ops
+            .download_file(&transport, &sources, &save_path, &expected, None)
+            .is_some());
+        assert_eq!(
+            *transport.ranges.borrow(),
+            vec![None, Some(format!("bytes={}-", data.len() / 2))]
+        );
+        assert_eq!(fs::read(&save_path).unwrap(), data);
+        let (part_path, info_path) = part_paths(&save_path);
+        assert!(!part_path.exists());
+        assert!(!info_path.exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_download_retry_and_failover() {
+        let dir = std::env::temp_dir().join(format!("rime_retry_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let save_path = dir.join("a.zip");
+        let ops = FileOperations::new(RetryPolicy {
+            attempts: 2,
+            base_delay: Duration::ZERO,
+        });
+        let sources: Vec<DownloadSource> = ["m1", "m2", "GitHub"]
+            .iter()
+            .map(|name| DownloadSource {
+                name: name.to_string(),
+                mirror: name.to_string(),
+                url: format!("https://{}/a.zip", name),
+            })
+            .collect();
+
+        let expected = ExpectedContent {
+            size: 2000,
+            ..Default::default()
+        };
+        // m1 两次 503 后放弃，m2 返回 404 直接跳过，GitHub 连接错误后重试成功
+        let transport = ScriptedTransport::new(&[Some(503), Some(503), Some(404), None, Some(200)]);
+        assert_eq!(
+            ops.download_file(&transport, &sources, &save_path, &expected, None),
+            Some("GitHub".to_string())
+        );
+        assert_eq!(transport.requests.borrow().len(), 5);
+        assert!(save_path.exists());
+
+        let transport = ScriptedTransport::new(&[Some(404), Some(500), Some(500), None, None]);
+        assert_eq!(
+            ops.download_file(&transport, &sources, &save_path, &expected, None),
+            None
+        );
+        // 全部失败时保留之前下载的文件
+        assert_eq!(fs::read(&save_path).unwrap(), vec![b'x'; 2000]);
+
+        // 内容不符的下载源不重试，直接换下一个
+        let expected = ExpectedContent {
+            format: FileFormat::Zip,
+            ..Default::default()
+        };
+        let transport = ScriptedTransport::new(&[Some(200), Some(200), Some(200)]);
+        assert_eq!(
+            ops.download_file(&transport, &sources, &save_path, &expected, None),
+            None
+        );
+        assert_eq!(transport.requests.borrow().len(), 3);
+
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(3), Duration::from_secs(4));
+        assert_eq!(policy.delay(10), MAX_RETRY_DELAY);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    fn build_zip(path: &Path, build: impl FnOnce(&mut zip::ZipWriter<File>)) {
+        let mut writer = zip::ZipWriter::new(File::create(path).unwrap());
+        build(&mut writer);
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_safe_relative_path() {
+        assert_eq!(
+            safe_relative_path("dicts/base.dict.yaml"),
+            Some(PathBuf::from("dicts").join("base.dict.yaml"))
+        );
+        assert_eq!(
+            safe_relative_path("./lua\\a.lua"),
+            Some(PathBuf::from("lua").join("a.lua"))
+        );
+        assert_eq!(safe_relative_path("../evil"), None);
+        assert_eq!(safe_relative_path("a/../../evil"), None);
+        assert_eq!(safe_relative_path("/etc/passwd"), None);
+        assert_eq!(safe_relative_path("\\\\server\\share"), None);
+        assert_eq!(safe_relative_path("C:/Windows/evil"), None);
+    }
+
+    #[test]
+    fn test_extract_archive() {
+        let dir = std::env::temp_dir().join(format!("rime_zip_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let options = SimpleFileOptions::default();
+        let none = PathFilter::default();
+
+        // 正常压缩包
+        let good = dir.join("good.zip");
+        build_zip(&good, |w| {
+            w.add_directory("dicts/", options).unwrap();
+            w.start_file("dicts/a.dict.yaml", options).unwrap();
+            w.write_all(b"hello").unwrap();
+        });
+        let out = dir.join("out");
+        assert_eq!(
+            extract_archive(&good, &out, &ExtractLimits::default(), &none).unwrap(),
+            (1, Vec::new())
+        );
+        assert_eq!(fs::read(out.join("dicts/a.dict.yaml")).unwrap(), b"hello");
+
+        // 按筛选规则跳过的文件
+        let filter = PathFilter::new(&FilterConfig {
+            include: Vec::new(),
+            exclude: vec!["*.dict.yaml".to_string()],
+        });
+        let filtered = dir.join("filtered");
+        assert_eq!(
+            extract_archive(&good, &filtered, &ExtractLimits::default(), &filter).unwrap(),
+            (0, vec![PathBuf::from("dicts").join("a.dict.yaml")])
+        );
+        assert!(!filtered.join("dicts/a.dict.yaml").exists());
+
+        // 路径穿越、符号链接
+        let slip = dir.join("slip.zip");
+        build_zip(&slip, |w| {
+            w.start_file("../evil.txt", options).unwrap();
+            w.write_all(b"evil").unwrap();
+        });
+        assert!(extract_archive(&slip, &out, &ExtractLimits::default(), &none).is_err());
+        assert!(!dir.join("evil.txt").exists());
+
+        let link = dir.join("link.zip");
+        build_zip(&link, |w| w.add_symlink("link", "/etc", options).unwrap());
+        assert!(extract_archive(&link, &out, &ExtractLimits::default(), &none).is_err());
+
+        // 条目数量和总大小限制
+        let limits = ExtractLimits {
+            max_entries: 1,
+            max_total_size: 4,
+        };
+        assert!(extract_archive(&good, &dir.join("limited"), &limits, &none).is_err());
+        let limits = ExtractLimits {
+            max_entries: 10,
+            max_total_size: 4,
+        };
+        assert!(extract_archive(&good, &dir.join("limited"), &limits, &none).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
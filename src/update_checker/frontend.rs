@@ -0,0 +1,221 @@
+use std::path::{Path, PathBuf};
+
+use super::{
+    fcitx5_manager::Fcitx5Manager, ibus_manager::IbusManager, weasel_manager::WeaselManager,
+};
+
+/// RIME 前端抽象
+///
+/// 各输入法前端（小狼毫、fcitx5-rime 等）的用户目录、共享数据目录
+/// 以及重新部署方式各不相同，统一通过该 trait 访问
+pub trait Frontend {
+    /// 前端显示名称
+    fn name(&self) -> &'static str;
+
+    /// 前端安装目录（程序所在目录）
+    fn root_dir(&self) -> PathBuf;
+
+    /// 定位用户目录
+    fn user_dir(&self) -> Result<PathBuf, Box<dyn std::error::Error>>;
+
+    /// 定位共享数据目录
+    fn shared_dir(&self) -> Result<PathBuf, Box<dyn std::error::Error>>;
+
+    /// 重新部署
+    fn deploy(&self) -> bool;
+
+    /// 停止前端服务
+    fn stop(&self);
+
+    /// 启动前端服务
+    fn start(&self);
+
+    /// 获取前端附带的工具路径（如 curl），默认从 PATH 中查找
+    fn bundled_tool(&self, name: &str) -> PathBuf {
+        find_in_path(name).unwrap_or_else(|| PathBuf::from(name))
+    }
+}
+
+/// 支持的前端类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrontendKind {
+    Weasel,
+    Fcitx5,
+    Ibus,
+}
+
+impl FrontendKind {
+    pub const ALL: [FrontendKind; 3] = [
+        FrontendKind::Weasel,
+        FrontendKind::Fcitx5,
+        FrontendKind::Ibus,
+    ];
+
+    /// 配置文件中使用的键值
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FrontendKind::Weasel => "weasel",
+            FrontendKind::Fcitx5 => "fcitx5",
+            FrontendKind::Ibus => "ibus",
+        }
+    }
+
+    /// 从配置文件中的键值解析
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "weasel" => Some(FrontendKind::Weasel),
+            "fcitx5" | "fcitx5-rime" => Some(FrontendKind::Fcitx5),
+            "ibus" | "ibus-rime" => Some(FrontendKind::Ibus),
+            _ => None,
+        }
+    }
+
+    /// 显示名称
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            FrontendKind::Weasel => "小狼毫",
+            FrontendKind::Fcitx5 => "fcitx5-rime",
+            FrontendKind::Ibus => "ibus-rime",
+        }
+    }
+
+    /// 检查该前端是否已安装
+    pub fn is_installed(&self) -> bool {
+        match self {
+            FrontendKind::Weasel => {
+                cfg!(target_os = "windows")
+                    && WeaselManager::locate()
+                        .map(|w| w.root_dir().join("WeaselDeployer.exe").exists())
+                        .unwrap_or(false)
+            }
+            FrontendKind::Fcitx5 => {
+                let user_dir_exists = Fcitx5Manager::new()
+                    .user_dir()
+                    .map(|p| p.exists())
+                    .unwrap_or(false);
+                find_in_path("fcitx5").is_some()
+                    && (user_dir_exists
+                        || PathBuf::from("/usr/share/fcitx5/addon/rime.conf").exists())
+            }
+            FrontendKind::Ibus => {
+                let user_dir_exists = IbusManager::new()
+                    .user_dir()
+                    .map(|p| p.exists())
+                    .unwrap_or(false);
+                find_in_path("ibus-daemon").is_some()
+                    && (user_dir_exists
+                        || PathBuf::from("/usr/share/ibus/component/rime.xml").exists())
+            }
+        }
+    }
+
+    /// 当前平台的默认前端
+    pub fn platform_default() -> Self {
+        if cfg!(target_os = "windows") {
+            FrontendKind::Weasel
+        } else {
+            FrontendKind::Fcitx5
+        }
+    }
+}
+
+/// 查找所有已安装的前端
+pub fn detect_installed() -> Vec<FrontendKind> {
+    FrontendKind::ALL
+        .into_iter()
+        .filter(|kind| kind.is_installed())
+        .collect()
+}
+
+/// 根据前端类型定位并创建前端实例
+pub fn locate(kind: FrontendKind) -> Result<Box<dyn Frontend>, Box<dyn std::error::Error>> {
+    match kind {
+        FrontendKind::Weasel => Ok(Box::new(WeaselManager::locate()?)),
+        FrontendKind::Fcitx5 => Ok(Box::new(Fcitx5Manager::new())),
+        FrontendKind::Ibus => Ok(Box::new(IbusManager::new())),
+    }
+}
+
+/// 根据前端类型和已知的安装目录创建前端实例
+pub fn create(kind: FrontendKind, root: &Path) -> Box<dyn Frontend> {
+    match kind {
+        FrontendKind::Weasel => Box::new(WeaselManager::new(root)),
+        FrontendKind::Fcitx5 => Box::new(Fcitx5Manager::with_root(root)),
+        FrontendKind::Ibus => Box::new(IbusManager::with_root(root)),
+    }
+}
+
+/// 查找工具程序，优先使用 root 目录中的
+pub fn find_tool(root: Option<&Path>, name: &str) -> PathBuf {
+    root.map(|root| root.join(name))
+        .filter(|path| path.is_file())
+        .or_else(|| find_in_path(name))
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+/// 查找 rime 共享数据目录
+///
+/// 指定了程序所在目录时优先查找同一前缀下的 share/rime-data
+pub fn find_shared_dir(root: Option<&Path>) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let prefixed = root
+        .into_iter()
+        .flat_map(|root| {
+            [
+                root.parent().map(Path::to_path_buf),
+                Some(root.to_path_buf()),
+            ]
+        })
+        .flatten()
+        .map(|prefix| prefix.join("share/rime-data"));
+    let system = ["/usr/share/rime-data", "/usr/local/share/rime-data"]
+        .into_iter()
+        .map(PathBuf::from);
+    prefixed
+        .chain(system)
+        .find(|path| path.exists())
+        .ok_or_else(|| "未找到 rime 共享数据目录".into())
+}
+
+/// 在 PATH 环境变量中查找可执行文件
+pub fn find_in_path(name: &str) -> Option<PathBuf> {
+    let path_var = std::env::var_os("PATH")?;
+    for dir in std::env::split_paths(&path_var) {
+        let candidate = dir.join(name);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if cfg!(target_os = "windows") {
+            let candidate = dir.join(format!("{}.exe", name));
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_linux_frontend_root() {
+        let prefix =
+            std::env::temp_dir().join(format!("rime_frontend_root_{}", std::process::id()));
+        let root = prefix.join("bin");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::create_dir_all(prefix.join("share/rime-data")).unwrap();
+        std::fs::write(root.join("curl"), "").unwrap();
+
+        for kind in [FrontendKind::Fcitx5, FrontendKind::Ibus] {
+            let frontend = create(kind, &root);
+            assert_eq!(frontend.root_dir(), root);
+            assert_eq!(frontend.bundled_tool("curl"), root.join("curl"));
+            assert_eq!(
+                frontend.shared_dir().unwrap(),
+                prefix.join("share/rime-data")
+            );
+        }
+        let _ = std::fs::remove_dir_all(&prefix);
+    }
+}
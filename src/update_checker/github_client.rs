@@ -1,49 +1,65 @@
+use super::transport::Transport;
+use crate::platform;
 use crate::types::*;
-use std::{path::PathBuf, process::Command};
+use crate::version::Version;
+use serde::de::DeserializeOwned;
+use std::rc::Rc;
 
 pub struct GitHubClient {
-    pub curl_path: PathBuf,
+    transport: Rc<dyn Transport>,
     pub config: UpdateConfig,
 }
 
 impl GitHubClient {
-    pub fn new(curl_path: &PathBuf, config: UpdateConfig) -> Self {
-        Self {
-            curl_path: curl_path.clone(),
-            config,
-        }
+    pub fn new(transport: Rc<dyn Transport>, config: UpdateConfig) -> Self {
+        Self { transport, config }
     }
 
     /// 检查方案更新
+    ///
+    /// 固定了版本时使用固定的版本，否则按发布渠道选择 Release
     pub fn check_schema_update(&self) -> Result<Option<UpdateInfo>, Box<dyn std::error::Error>> {
         println!("🔍 检查方案更新...");
+        let pin = self.config.pins.schema_version.trim();
+        let channel = self.config.channels.schema;
+
+        if pin.is_empty() && channel == Channel::Nightly {
+            let tag = &self.config.channels.schema_nightly_tag;
+            println!("🌙 方案使用 nightly 渠道，跟随标签 {}", tag);
+            let api_url = format!(
+                "https://api.github.com/repos/{}/releases/tags/{}",
+                self.config.schema_repo, tag
+            );
+            return match self.fetch_release_info(&api_url)? {
+                Some(release_info) => Ok(self.schema_update_info(&release_info, false)),
+                None => {
+                    println!("❌ 方案更新检查失败");
+                    Ok(None)
+                }
+            };
+        }
+
         let api_url = format!(
             "https://api.github.com/repos/{}/releases",
             self.config.schema_repo
         );
 
         if let Some(releases) = self.fetch_releases_info(&api_url)? {
-            // 查找第一个匹配版本号格式的 release
-            if let Some(release_info) = self.find_version_release(&releases) {
-                println!("✅ 找到版本 release: {}", release_info.tag_name);
-
-                // 查找方案相关的资产
-                if let Some(asset) = self.find_schema_asset(&release_info.assets) {
-                    println!("✅ 找到方案资产: {}", asset.name);
-                    return Ok(Some(UpdateInfo {
-                        tag: release_info.tag_name.clone(),
-                        file_name: asset.name.clone(),
-                        file_size: asset.size,
-                        url: self.convert_to_mirror_url(&asset.browser_download_url),
-                        sha3_256: asset.sha3_256.clone(),
-                        update_time: release_info.published_at.clone(),
-                        description: release_info.body.as_ref().unwrap_or(&String::new()).clone(),
-                    }));
-                } else {
-                    println!("❌ 未找到方案相关的资产文件");
-                }
+            let release_info = if pin.is_empty() {
+                // 查找第一个符合渠道的版本号格式的 release
+                self.find_version_release(&releases, channel).cloned()
             } else {
+                println!("📌 方案固定在版本 {}", pin);
+                self.find_pinned_release(&releases, pin)?
+            };
+
+            if let Some(release_info) = release_info {
+                println!("✅ 找到版本 release: {}", release_info.tag_name);
+                return Ok(self.schema_update_info(&release_info, !pin.is_empty()));
+            } else if pin.is_empty() {
                 println!("❌ 未找到匹配版本号格式的 release");
+            } else {
+                println!("❌ 未找到固定的方案版本 {}", pin);
             }
         } else {
             println!("❌ 方案更新检查失败");
@@ -51,6 +67,49 @@ impl GitHubClient {
         Ok(None)
     }
 
+    /// 在 Release 中查找方案资产并生成更新信息
+    fn schema_update_info(&self, release: &GitHubRelease, pinned: bool) -> Option<UpdateInfo> {
+        match self.find_schema_asset(&release.assets) {
+            Some(asset) => {
+                println!("✅ 找到方案资产: {}", asset.name);
+                let mut info = self.build_update_info(release, asset);
+                info.pinned = pinned;
+                Some(info)
+            }
+            None => {
+                println!("❌ 未找到方案相关的资产文件");
+                None
+            }
+        }
+    }
+
+    /// 在 Release 列表中查找固定的版本，列表中没有时按标签单独查询
+    ///
+    /// 两者都是版本号时按版本比较，`10.0` 与 `v10.0.0` 视为同一版本
+    fn find_pinned_release(
+        &self,
+        releases: &[GitHubRelease],
+        pin: &str,
+    ) -> Result<Option<GitHubRelease>, Box<dyn std::error::Error>> {
+        let pinned_version = Version::parse(pin);
+        let found = releases.iter().find(|release| {
+            release.tag_name == pin
+                || matches!(
+                    (&pinned_version, Version::parse(&release.tag_name)),
+                    (Some(pinned), Some(version)) if *pinned == version
+                )
+        });
+        if let Some(release) = found {
+            return Ok(Some(release.clone()));
+        }
+
+        let api_url = format!(
+            "https://api.github.com/repos/{}/releases/tags/{}",
+            self.config.schema_repo, pin
+        );
+        self.fetch_release_info(&api_url)
+    }
+
     /// 检查字典更新
     pub fn check_dict_update(&self) -> Result<Option<UpdateInfo>, Box<dyn std::error::Error>> {
         println!("🔍 检查词库更新...");
@@ -62,15 +121,19 @@ impl GitHubClient {
             // 查找字典相关的资产
             if let Some(asset) = self.find_dict_asset(&release_info.assets) {
                 println!("✅ 找到词库资产: {}", asset.name);
-                return Ok(Some(UpdateInfo {
-                    tag: release_info.tag_name,
-                    file_name: asset.name.clone(),
-                    file_size: asset.size,
-                    url: self.convert_to_mirror_url(&asset.browser_download_url),
-                    sha3_256: asset.sha3_256.clone(),
-                    update_time: release_info.published_at,
-                    description: release_info.body.unwrap_or_default(),
-                }));
+                let pins = &self.config.pins;
+                let pinned = pins.dict_asset_id.is_some() || !pins.dict_date.is_empty();
+                if pinned && !Self::matches_dict_pin(pins, asset) {
+                    // 固定标签下只保留最新的资产，固定的资产被替换后无法再下载
+                    println!(
+                        "⚠️ 固定的词库资产已不可用 (当前资产 ID {}，更新于 {})，跳过词库更新",
+                        asset.id, asset.updated_at
+                    );
+                    return Ok(None);
+                }
+                let mut info = self.build_update_info(&release_info, asset);
+                info.pinned = pinned;
+                return Ok(Some(info));
             } else {
                 println!("❌ 未找到词库相关的资产文件");
             }
@@ -83,25 +146,25 @@ impl GitHubClient {
     /// 检查模型更新
     pub fn check_model_update(&self) -> Result<Option<UpdateInfo>, Box<dyn std::error::Error>> {
         println!("🔍 检查模型更新...");
+        let pin = self.config.pins.model_version.trim();
+        let tag = if pin.is_empty() {
+            self.config.model_tag.as_str()
+        } else {
+            println!("📌 模型固定在 {}", pin);
+            pin
+        };
         let api_url = format!(
             "https://api.github.com/repos/{}/releases/tags/{}",
-            self.config.model_repo, self.config.model_tag
+            self.config.model_repo, tag
         );
-        dbg!(&api_url);
 
         if let Some(release_info) = self.fetch_release_info(&api_url)? {
             // 查找模型相关的资产
             if let Some(asset) = self.find_model_asset(&release_info.assets) {
                 println!("✅ 找到模型资产: {}", asset.name);
-                return Ok(Some(UpdateInfo {
-                    tag: release_info.tag_name,
-                    file_name: asset.name.clone(),
-                    file_size: asset.size,
-                    url: self.convert_to_mirror_url(&asset.browser_download_url),
-                    sha3_256: asset.sha3_256.clone(),
-                    update_time: release_info.published_at,
-                    description: release_info.body.unwrap_or_default(),
-                }));
+                let mut info = self.build_update_info(&release_info, asset);
+                info.pinned = !pin.is_empty();
+                return Ok(Some(info));
             } else {
                 println!("❌ 未找到模型相关的资产文件");
             }
@@ -112,17 +175,41 @@ impl GitHubClient {
     }
 
     /// 检查程序自身更新
+    ///
+    /// stable 使用 latest 接口；beta 在 Release 列表中查找包括预发布版本在内的最新版本；
+    /// nightly 跟随滚动标签，标签不是版本号，是否更新由资产变化决定
     pub fn check_self_update(&self) -> Result<Option<UpdateInfo>, Box<dyn std::error::Error>> {
         println!("🔍 检查程序自身更新...");
-        let api_url = format!(
-            "https://api.github.com/repos/{}/releases/latest",
-            self.config.self_repo
-        );
-        if let Some(release_info) = self.fetch_release_info(&api_url)? {
+        let channel = self.config.channels.self_update;
+        let repo = &self.config.self_repo;
+        let release_info = match channel {
+            Channel::Stable => self.fetch_release_info(&format!(
+                "https://api.github.com/repos/{}/releases/latest",
+                repo
+            ))?,
+            Channel::Beta => self
+                .fetch_releases_info(&format!("https://api.github.com/repos/{}/releases", repo))?
+                .and_then(|releases| self.find_version_release(&releases, channel).cloned()),
+            Channel::Nightly => {
+                let tag = &self.config.channels.self_nightly_tag;
+                println!("🌙 程序使用 nightly 渠道，跟随标签 {}", tag);
+                self.fetch_release_info(&format!(
+                    "https://api.github.com/repos/{}/releases/tags/{}",
+                    repo, tag
+                ))?
+            }
+        };
+
+        if let Some(release_info) = release_info {
             // 查找程序相关的资产
             if let Some(asset) = self.find_self_asset(&release_info.assets) {
+                if channel == Channel::Nightly {
+                    println!("✅ 找到程序资产: {}", asset.name);
+                    return Ok(Some(self.build_update_info(&release_info, asset)));
+                }
+
                 // 检查版本是否比当前版本更新
-                let current_version = VERSION.to_string();
+                let current_version = VERSION;
                 let remote_version = &release_info.tag_name;
 
                 println!(
@@ -130,17 +217,20 @@ impl GitHubClient {
                     current_version, remote_version
                 );
 
-                if compare_version(remote_version.clone(), current_version) {
+                let is_newer = match (
+                    Version::parse(remote_version),
+                    Version::parse(current_version),
+                ) {
+                    (Some(remote), Some(current)) => remote > current,
+                    _ => {
+                        println!("⚠️ 无法解析远程版本号: {}", remote_version);
+                        false
+                    }
+                };
+
+                if is_newer {
                     println!("✅ 找到程序更新: {}", asset.name);
-                    return Ok(Some(UpdateInfo {
-                        tag: release_info.tag_name,
-                        file_name: asset.name.clone(),
-                        file_size: asset.size,
-                        url: self.convert_to_mirror_url(&asset.browser_download_url),
-                        sha3_256: asset.sha3_256.clone(),
-                        update_time: release_info.published_at,
-                        description: release_info.body.unwrap_or_default(),
-                    }));
+                    return Ok(Some(self.build_update_info(&release_info, asset)));
                 } else {
                     println!("✅ 程序已是最新版本");
                 }
@@ -153,20 +243,27 @@ impl GitHubClient {
         Ok(None)
     }
 
-    /// 将 GitHub 下载链接转换为镜像站链接
-    fn convert_to_mirror_url(&self, github_url: &str) -> String {
-        // 检查镜像站配置是否不为空
-        if !self.config.mirror.is_empty() {
-            // 构建完整的镜像站 URL
-            let mirror_url = if self.config.mirror.starts_with("http") {
-                self.config.mirror.clone()
-            } else {
-                format!("https://{}", self.config.mirror)
-            };
-            // 将 GitHub 链接转换为镜像站链接
-            return format!("{}/{}", mirror_url, github_url);
+    /// 根据 Release 和资产信息生成更新信息
+    fn build_update_info(&self, release: &GitHubRelease, asset: &GitHubAsset) -> UpdateInfo {
+        UpdateInfo {
+            tag: release.tag_name.clone(),
+            file_name: asset.name.clone(),
+            file_size: asset.size,
+            url: asset.browser_download_url.clone(),
+            sha3_256: asset.sha3_256.clone(),
+            update_time: release.published_at.clone(),
+            description: release.body.clone().unwrap_or_default(),
+            asset_id: asset.id,
+            asset_updated_at: asset.updated_at.clone(),
+            digest: asset.digest.clone(),
+            pinned: false,
         }
-        github_url.to_string()
+    }
+
+    /// 词库资产是否符合 [pins] 中固定的资产 ID 和更新日期
+    fn matches_dict_pin(pins: &PinConfig, asset: &GitHubAsset) -> bool {
+        pins.dict_asset_id.is_none_or(|id| id == asset.id)
+            && (pins.dict_date.is_empty() || asset.updated_at.starts_with(pins.dict_date.trim()))
     }
 
     /// 获取GitHub Releases列表信息
@@ -174,54 +271,11 @@ impl GitHubClient {
         &self,
         api_url: &str,
     ) -> Result<Option<Vec<GitHubRelease>>, Box<dyn std::error::Error>> {
-        let output = Command::new(&self.curl_path)
-            .args(&[
-                "-s",
-                "-H",
-                "Accept: application/vnd.github.v3+json",
-                "-H",
-                "User-Agent: rime_wanxiang_updater",
-                api_url,
-            ])
-            .output()?;
-
-        if output.status.success() {
-            let response = String::from_utf8(output.stdout)?;
-
-            // 检查是否是 API 错误响应
-            if response.contains("\"message\"") && response.contains("\"documentation_url\"") {
-                eprintln!("❌ GitHub API 请求失败!");
-                eprintln!("请求 URL: {}", api_url);
-                eprintln!("完整响应内容: {}", response);
-
-                if let Ok(error) = serde_json::from_str::<GitHubApiError>(&response) {
-                    eprintln!("错误消息: {}", error.message);
-                    if let Some(doc_url) = &error.documentation_url {
-                        eprintln!("文档地址: {}", doc_url);
-                    }
-                }
-                return Ok(None);
-            }
-
-            match serde_json::from_str::<Vec<GitHubRelease>>(&response) {
-                Ok(releases) => {
-                    dbg!("✅ 成功解析 {} 个 Releases", releases.len());
-                    Ok(Some(releases))
-                }
-                Err(e) => {
-                    eprintln!("❌ 解析GitHub Releases响应失败!");
-                    eprintln!("请求 URL: {}", api_url);
-                    eprintln!("解析错误: {}", e);
-                    eprintln!("完整响应内容: {}", response);
-                    Ok(None)
-                }
-            }
-        } else {
-            eprintln!("❌ curl 请求失败!");
-            eprintln!("请求 URL: {}", api_url);
-            eprintln!("错误信息: {}", String::from_utf8_lossy(&output.stderr));
-            Ok(None)
+        let releases = self.fetch_json::<Vec<GitHubRelease>>(api_url)?;
+        if let Some(releases) = &releases {
+            println!("✅ 成功解析 {} 个 Releases", releases.len());
         }
+        Ok(releases)
     }
 
     /// 获取GitHub Release信息 (单个 release)
@@ -229,59 +283,75 @@ impl GitHubClient {
         &self,
         api_url: &str,
     ) -> Result<Option<GitHubRelease>, Box<dyn std::error::Error>> {
-        let output = Command::new(&self.curl_path)
-            .args(&[
-                "-s",
-                "-H",
-                "Accept: application/vnd.github.v3+json",
-                "-H",
-                "User-Agent: rime_wanxiang_updater",
-                api_url,
-            ])
-            .output()?;
-
-        if output.status.success() {
-            let response = String::from_utf8(output.stdout)?;
-
-            // 检查是否是 API 错误响应
-            if response.contains("\"message\"") && response.contains("\"documentation_url\"") {
-                eprintln!("❌ GitHub API 请求失败!");
-                eprintln!("请求 URL: {}", api_url);
-                eprintln!("完整响应内容: {}", response);
+        let release = self.fetch_json::<GitHubRelease>(api_url)?;
+        if let Some(release) = &release {
+            println!(
+                "✅ 成功解析 Release: {} ({})",
+                release.tag_name, release.published_at
+            );
+        }
+        Ok(release)
+    }
 
-                if let Ok(error) = serde_json::from_str::<GitHubApiError>(&response) {
-                    eprintln!("错误消息: {}", error.message);
-                    if let Some(doc_url) = &error.documentation_url {
-                        eprintln!("文档地址: {}", doc_url);
-                    }
-                }
+    /// 请求 GitHub API 并解析 JSON，根据 HTTP 状态码判断是否出错
+    fn fetch_json<T: DeserializeOwned>(
+        &self,
+        api_url: &str,
+    ) -> Result<Option<T>, Box<dyn std::error::Error>> {
+        let response = match self
+            .transport
+            .get(api_url, &[("Accept", "application/vnd.github.v3+json")])
+        {
+            Ok(response) => response,
+            Err(e) => {
+                eprintln!("❌ {} 请求失败!", self.transport.name());
+                eprintln!("请求 URL: {}", api_url);
+                eprintln!("错误信息: {}", e);
                 return Ok(None);
             }
+        };
 
-            match serde_json::from_str::<GitHubRelease>(&response) {
-                Ok(release) => {
-                    println!(
-                        "✅ 成功解析 Release: {} ({})",
-                        release.tag_name, release.published_at
-                    );
-                    Ok(Some(release))
-                }
-                Err(e) => {
-                    eprintln!("❌ 解析GitHub Release响应失败!");
-                    eprintln!("请求 URL: {}", api_url);
-                    eprintln!("解析错误: {}", e);
-                    eprintln!("完整响应内容: {}", response);
-                    Ok(None)
+        if !response.is_success() {
+            eprintln!("❌ GitHub API 请求失败! (HTTP {})", response.status);
+            eprintln!("请求 URL: {}", api_url);
+
+            if let Ok(error) = serde_json::from_str::<GitHubApiError>(&response.body) {
+                eprintln!("错误消息: {}", error.message);
+                if let Some(doc_url) = &error.documentation_url {
+                    eprintln!("文档地址: {}", doc_url);
                 }
+            } else {
+                eprintln!("完整响应内容: {}", response.body);
+            }
+
+            if matches!(response.status, 403 | 429)
+                && response.header("X-RateLimit-Remaining") == Some("0")
+            {
+                eprintln!("⚠️ 已达到 GitHub API 访问频率限制，可稍后重试或配置 github_cookies");
+            }
+            return Ok(None);
+        }
+
+        match serde_json::from_str::<T>(&response.body) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) => {
+                eprintln!("❌ 解析GitHub API响应失败!");
+                eprintln!("请求 URL: {}", api_url);
+                eprintln!("解析错误: {}", e);
+                eprintln!("完整响应内容: {}", response.body);
+                Ok(None)
             }
-        } else {
-            eprintln!("❌ curl 请求失败!");
-            eprintln!("请求 URL: {}", api_url);
-            eprintln!("错误信息: {}", String::from_utf8_lossy(&output.stderr));
-            Ok(None)
         }
     }
-    fn find_version_release<'a>(&self, releases: &'a [GitHubRelease]) -> Option<&'a GitHubRelease> {
+
+    /// 查找第一个版本号格式的 Release
+    ///
+    /// stable 渠道跳过草稿以及标记为预发布或标签带预发布标识（如 `-beta`）的版本
+    fn find_version_release<'a>(
+        &self,
+        releases: &'a [GitHubRelease],
+        channel: Channel,
+    ) -> Option<&'a GitHubRelease> {
         for release in releases {
             let tag = &release.tag_name;
             // 匹配 v 开头的版本号格式: v10.2.3 或 v19.2.3-beta
@@ -291,6 +361,13 @@ impl GitHubClient {
                 if version_part.chars().any(|c| c.is_numeric())
                     && version_part.chars().any(|c| c == '.')
                 {
+                    let is_prerelease = release.prerelease
+                        || release.draft
+                        || Version::parse(tag).is_some_and(|v| v.is_prerelease());
+                    if channel == Channel::Stable && is_prerelease {
+                        println!("跳过预发布版本: {}", tag);
+                        continue;
+                    }
                     println!("找到匹配的版本标签: {}", release.tag_name);
                     return Some(release);
                 }
@@ -366,12 +443,8 @@ impl GitHubClient {
 
     /// 查找程序相关的资产文件
     fn find_self_asset<'a>(&self, assets: &'a [GitHubAsset]) -> Option<&'a GitHubAsset> {
-        for asset in assets {
-            let name = asset.name.to_lowercase();
-            if name.ends_with(".exe") {
-                return Some(asset);
-            }
-        }
-        None
+        assets
+            .iter()
+            .find(|asset| platform::is_self_asset(&asset.name))
     }
 }
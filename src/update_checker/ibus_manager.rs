@@ -0,0 +1,127 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::Duration,
+};
+
+use super::frontend::{self, find_in_path, Frontend};
+
+/// ibus-rime 前端
+pub struct IbusManager {
+    home: PathBuf,
+    /// 手动指定的程序所在目录
+    root: Option<PathBuf>,
+}
+
+impl IbusManager {
+    pub fn new() -> Self {
+        Self {
+            home: std::env::var_os("HOME")
+                .map(PathBuf::from)
+                .unwrap_or_default(),
+            root: None,
+        }
+    }
+
+    /// 使用指定的程序所在目录（如 /opt/ibus-daemon/bin）
+    pub fn with_root(root: &Path) -> Self {
+        Self {
+            root: Some(root.to_path_buf()),
+            ..Self::new()
+        }
+    }
+
+    /// 通过 ibus-daemon 重启的方式重新加载
+    fn restart_daemon(&self) -> bool {
+        match Command::new(self.bundled_tool("ibus-daemon"))
+            .arg("-rdx")
+            .status()
+        {
+            Ok(status) if status.success() => true,
+            Ok(status) => {
+                eprintln!("❌ ibus-daemon 执行失败，状态码: {}", status);
+                false
+            }
+            Err(e) => {
+                eprintln!("❌ 无法调用 ibus-daemon: {}", e);
+                false
+            }
+        }
+    }
+}
+
+impl Default for IbusManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Frontend for IbusManager {
+    fn name(&self) -> &'static str {
+        "ibus-rime"
+    }
+
+    fn root_dir(&self) -> PathBuf {
+        if let Some(root) = &self.root {
+            return root.clone();
+        }
+        find_in_path("ibus-daemon")
+            .and_then(|p| p.parent().map(|p| p.to_path_buf()))
+            .unwrap_or_else(|| PathBuf::from("/usr/bin"))
+    }
+
+    fn user_dir(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        if self.home.as_os_str().is_empty() {
+            return Err("未设置 HOME 环境变量，无法定位 ibus-rime 用户目录".into());
+        }
+        Ok(self.home.join(".config/ibus/rime"))
+    }
+
+    fn shared_dir(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        frontend::find_shared_dir(self.root.as_deref())
+    }
+
+    /// 部署 ibus-rime，优先使用 ibus restart，失败时回退到 ibus-daemon -rdx
+    fn deploy(&self) -> bool {
+        println!("正在部署 ibus-rime...");
+
+        let restarted = match Command::new(self.bundled_tool("ibus"))
+            .arg("restart")
+            .status()
+        {
+            Ok(status) if status.success() => true,
+            Ok(status) => {
+                eprintln!("⚠️ ibus restart 执行失败，状态码: {}", status);
+                false
+            }
+            Err(e) => {
+                eprintln!("⚠️ 无法调用 ibus: {}", e);
+                false
+            }
+        };
+
+        if restarted || self.restart_daemon() {
+            println!("✅ ibus-rime 部署成功");
+            true
+        } else {
+            eprintln!("❌ ibus-rime 部署失败");
+            false
+        }
+    }
+
+    fn stop(&self) {
+        println!("正在停止 ibus...");
+        let _ = Command::new(self.bundled_tool("ibus")).arg("exit").status();
+        thread::sleep(Duration::from_secs(1));
+    }
+
+    fn start(&self) {
+        println!("正在启动 ibus...");
+        let _ = self.restart_daemon();
+    }
+
+    fn bundled_tool(&self, name: &str) -> PathBuf {
+        frontend::find_tool(self.root.as_deref(), name)
+    }
+}
@@ -0,0 +1,162 @@
+use super::manifest::Manifest;
+use crate::{run_log::unix_now, types::UpdateInfo};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// 状态文件名，位于状态目录中
+pub const STATE_FILENAME: &str = "installed.json";
+
+/// 各组件的安装状态，与下载缓存无关，清空缓存不影响更新判断
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InstallState {
+    #[serde(default)]
+    pub components: BTreeMap<String, ComponentState>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentState {
+    /// 当前已安装的版本（标签、资产标识和摘要），从未安装成功时为 None
+    pub installed: Option<UpdateInfo>,
+    /// 安装时间（unix 秒），从未安装成功时为 0
+    #[serde(default)]
+    pub installed_at: u64,
+    /// 本次安装写入的目标路径
+    #[serde(default)]
+    pub targets: Vec<PathBuf>,
+    /// 最近一次更新尝试的结果
+    pub last_result: LastResult,
+    /// 本次安装写入的文件及其哈希
+    #[serde(default)]
+    pub manifest: Manifest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastResult {
+    pub success: bool,
+    pub tag: String,
+    pub time: u64,
+}
+
+impl InstallState {
+    /// 读取状态文件，不存在或无法解析时返回空状态
+    pub fn load(path: &Path) -> Self {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => return Self::default(),
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            eprintln!("⚠️ 无法解析状态文件 {:?}: {}，视为未安装", path, e);
+            Self::default()
+        })
+    }
+
+    /// 写入状态文件，先写临时文件再重命名，避免中断时留下损坏的文件
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    pub fn installed(&self, component: &str) -> Option<&UpdateInfo> {
+        self.components
+            .get(component)
+            .and_then(|c| c.installed.as_ref())
+    }
+
+    /// 组件上次安装的文件清单，没有记录时为空
+    pub fn manifest(&self, component: &str) -> Manifest {
+        self.components
+            .get(component)
+            .map(|c| c.manifest.clone())
+            .unwrap_or_default()
+    }
+
+    /// 其他组件清单中的全部文件
+    pub fn paths_owned_by_others(&self, component: &str) -> HashSet<PathBuf> {
+        self.components
+            .iter()
+            .filter(|(name, _)| name.as_str() != component)
+            .flat_map(|(_, state)| state.manifest.paths())
+            .collect()
+    }
+
+    /// 记录组件已成功安装
+    pub fn mark_installed(
+        &mut self,
+        component: &str,
+        info: &UpdateInfo,
+        targets: Vec<PathBuf>,
+        manifest: Manifest,
+    ) {
+        let now = unix_now();
+        self.components.insert(
+            component.to_string(),
+            ComponentState {
+                installed: Some(info.clone()),
+                installed_at: now,
+                targets,
+                last_result: LastResult {
+                    success: true,
+                    tag: info.tag.clone(),
+                    time: now,
+                },
+                manifest,
+            },
+        );
+    }
+
+    /// 回滚后恢复为更新前的版本，更新前未安装则删除记录
+    pub fn mark_rolled_back(
+        &mut self,
+        component: &str,
+        previous: Option<&UpdateInfo>,
+        previous_manifest: &Manifest,
+    ) {
+        match previous {
+            Some(previous) => {
+                if let Some(state) = self.components.get_mut(component) {
+                    state.installed = Some(previous.clone());
+                    state.installed_at = unix_now();
+                    state.manifest = previous_manifest.clone();
+                }
+            }
+            None => {
+                self.components.remove(component);
+            }
+        }
+    }
+
+    /// 记录更新失败，已安装的版本保持不变
+    ///
+    /// 首次安装失败时也会留下记录，但不视为已安装
+    pub fn mark_failed(&mut self, component: &str, info: &UpdateInfo) {
+        let last_result = LastResult {
+            success: false,
+            tag: info.tag.clone(),
+            time: unix_now(),
+        };
+        match self.components.get_mut(component) {
+            Some(state) => state.last_result = last_result,
+            None => {
+                self.components.insert(
+                    component.to_string(),
+                    ComponentState {
+                        installed: None,
+                        installed_at: 0,
+                        targets: Vec::new(),
+                        last_result,
+                        manifest: Manifest::default(),
+                    },
+                );
+            }
+        }
+    }
+}
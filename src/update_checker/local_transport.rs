@@ -0,0 +1,279 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::{Component, Path, PathBuf},
+    thread,
+};
+
+use super::transport::{DownloadResult, HttpResponse, NativeTransport, Transport};
+
+/// 从本地目录读取预置的 Release JSON 和资产文件
+///
+/// URL `https://<host>/<path>` 对应文件 `<root>/<host>/<path>`，
+/// 若对应的是目录则读取其中的 `index.json`。
+/// 镜像站形式的 URL（`https://<mirror>/https://github.com/...`）按原始地址处理。
+///
+/// 目录结构示例：
+/// ```text
+/// api.github.com/repos/amzxyz/rime_wanxiang/releases/index.json
+/// api.github.com/repos/amzxyz/rime_wanxiang/releases/tags/dict-nightly/index.json
+/// github.com/amzxyz/rime_wanxiang/releases/download/v1.0.0/rime-wanxiang-base.zip
+/// ```
+pub struct LocalTransport {
+    root: PathBuf,
+}
+
+impl LocalTransport {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+}
+
+impl Transport for LocalTransport {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn get(
+        &self,
+        url: &str,
+        _headers: &[(&str, &str)],
+    ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+        match resolve_local_file(&self.root, url) {
+            Some(path) => Ok(HttpResponse {
+                status: 200,
+                headers: vec![("Content-Type".to_string(), content_type(&path).to_string())],
+                body: fs::read_to_string(&path)?,
+            }),
+            None => Ok(HttpResponse {
+                status: 404,
+                headers: Vec::new(),
+                body: NOT_FOUND_BODY.to_string(),
+            }),
+        }
+    }
+
+    fn download(
+        &self,
+        url: &str,
+        save_path: &Path,
+        _headers: &[(&str, &str)],
+    ) -> Result<DownloadResult, Box<dyn std::error::Error>> {
+        match resolve_local_file(&self.root, url) {
+            Some(path) => {
+                let bytes_written = fs::copy(&path, save_path)?;
+                Ok(DownloadResult {
+                    status: 200,
+                    content_length: Some(bytes_written),
+                    content_type: Some(content_type(&path).to_string()),
+                    etag: None,
+                    bytes_written,
+                })
+            }
+            None => Ok(DownloadResult {
+                status: 404,
+                content_length: None,
+                content_type: None,
+                etag: None,
+                bytes_written: 0,
+            }),
+        }
+    }
+
+    fn fetch_prefix(
+        &self,
+        url: &str,
+        _headers: &[(&str, &str)],
+        limit: u64,
+    ) -> Result<(u16, u64), Box<dyn std::error::Error>> {
+        match resolve_local_file(&self.root, url) {
+            Some(path) => Ok((200, fs::metadata(&path)?.len().min(limit))),
+            None => Ok((404, 0)),
+        }
+    }
+}
+
+const NOT_FOUND_BODY: &str =
+    r#"{"message":"Not Found","documentation_url":"https://docs.github.com/rest"}"#;
+
+/// 将 URL 转换为本地目录下的相对路径，拒绝包含 `..` 的路径
+fn url_to_relative(url: &str) -> Option<PathBuf> {
+    // 镜像站 URL 中嵌套了原始地址，取最后一个 scheme 之后的部分
+    let rest = url.rsplit_once("://").map(|(_, r)| r).unwrap_or(url);
+    let rest = rest.split(['?', '#']).next().unwrap_or_default();
+
+    let relative = PathBuf::from(rest.trim_matches('/'));
+    if relative.as_os_str().is_empty()
+        || relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return None;
+    }
+    Some(relative)
+}
+
+fn resolve_local_file(root: &Path, url: &str) -> Option<PathBuf> {
+    let path = root.join(url_to_relative(url)?);
+    let path = if path.is_dir() {
+        path.join("index.json")
+    } else {
+        path
+    };
+    path.is_file().then_some(path)
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => "application/json; charset=utf-8",
+        Some("zip") => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+// ===== 本地 HTTP 服务 =====
+
+/// 在 localhost 上以 HTTP 提供本地目录中的文件
+///
+/// 目录结构与 [`LocalTransport`] 相同，服务线程随进程退出
+pub struct LocalServer {
+    addr: SocketAddr,
+}
+
+impl LocalServer {
+    /// 在随机端口上启动服务
+    pub fn start(root: &Path) -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let root = root.to_path_buf();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().map_while(Result::ok) {
+                let root = root.clone();
+                thread::spawn(move || {
+                    let _ = serve_connection(&root, stream);
+                });
+            }
+        });
+
+        println!("本地测试服务已启动: http://{}", addr);
+        Ok(Self { addr })
+    }
+
+    /// 创建将所有请求转发到本服务的传输实例
+    pub fn transport(&self) -> LocalServerTransport {
+        LocalServerTransport {
+            base: format!("http://{}", self.addr),
+            inner: NativeTransport::new(),
+        }
+    }
+}
+
+/// 处理单个 HTTP 连接（仅支持 GET）
+fn serve_connection(root: &Path, mut stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // 读取并丢弃请求头
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && line.trim_end() != "" {
+        line.clear();
+    }
+
+    let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let file = resolve_local_file(root, &format!("http:/{}", target));
+
+    match file.map(|path| fs::read(&path).map(|data| (path, data))) {
+        Some(Ok((path, data))) => {
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                content_type(&path),
+                data.len()
+            )?;
+            stream.write_all(&data)?;
+        }
+        _ => {
+            write!(
+                stream,
+                "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                NOT_FOUND_BODY.len(),
+                NOT_FOUND_BODY
+            )?;
+        }
+    }
+    stream.flush()
+}
+
+/// 将请求地址改写到本地 HTTP 服务的传输
+pub struct LocalServerTransport {
+    base: String,
+    inner: NativeTransport,
+}
+
+impl LocalServerTransport {
+    fn rewrite(&self, url: &str) -> String {
+        match url_to_relative(url) {
+            Some(relative) => format!(
+                "{}/{}",
+                self.base,
+                relative.to_string_lossy().replace('\\', "/")
+            ),
+            None => format!("{}/", self.base),
+        }
+    }
+}
+
+impl Transport for LocalServerTransport {
+    fn name(&self) -> &'static str {
+        "local-server"
+    }
+
+    fn get(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+        self.inner.get(&self.rewrite(url), headers)
+    }
+
+    fn download(
+        &self,
+        url: &str,
+        save_path: &Path,
+        headers: &[(&str, &str)],
+    ) -> Result<DownloadResult, Box<dyn std::error::Error>> {
+        self.inner.download(&self.rewrite(url), save_path, headers)
+    }
+
+    fn fetch_prefix(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        limit: u64,
+    ) -> Result<(u16, u64), Box<dyn std::error::Error>> {
+        self.inner.fetch_prefix(&self.rewrite(url), headers, limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_to_relative() {
+        assert_eq!(
+            url_to_relative("https://api.github.com/repos/a/b/releases?per_page=10"),
+            Some(PathBuf::from("api.github.com/repos/a/b/releases"))
+        );
+        assert_eq!(
+            url_to_relative("https://gh-proxy.com/https://github.com/a/b/x.zip"),
+            Some(PathBuf::from("github.com/a/b/x.zip"))
+        );
+        assert_eq!(url_to_relative("https://github.com/../etc/passwd"), None);
+    }
+}
@@ -0,0 +1,190 @@
+//! 已安装文件清单
+//!
+//! 记录每个组件安装的文件及其哈希，用于在下次更新时找出上游已删除的文件，
+//! 以及安装后被用户修改过、将被新版本覆盖的文件。
+
+use crate::{
+    file_checker,
+    types::{ConflictPolicy, RemovedFilePolicy},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::staging;
+
+/// 组件安装的文件清单
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// 文件所在的目标目录
+    pub root: PathBuf,
+    /// 相对路径（以 `/` 分隔）到 SHA3-256 哈希的映射
+    pub files: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    /// 计算 source_dir 中 files 的哈希，生成安装到 root 后的清单
+    pub fn build(root: &Path, source_dir: &Path, files: &[PathBuf]) -> io::Result<Self> {
+        let mut manifest = Manifest {
+            root: root.to_path_buf(),
+            files: BTreeMap::new(),
+        };
+        for relative in files {
+            manifest.add(relative, &source_dir.join(relative))?;
+        }
+        Ok(manifest)
+    }
+
+    /// 以 source 的内容记录安装到 root 下 relative 的文件
+    pub fn add(&mut self, relative: &Path, source: &Path) -> io::Result<()> {
+        let hash = file_checker::hash_file(source)?;
+        self.files.insert(key(relative), hash);
+        Ok(())
+    }
+
+    /// 清单中所有文件的完整路径
+    pub fn paths(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.files.keys().map(|k| self.root.join(k))
+    }
+
+    /// 旧清单中有、本清单中没有的文件（相对路径）
+    ///
+    /// 只返回仍然存在、未被修改且不属于 other_paths 的文件；目标目录变化时不处理
+    pub fn removed_since(&self, old: &Manifest, other_paths: &HashSet<PathBuf>) -> Vec<PathBuf> {
+        if old.root != self.root {
+            return Vec::new();
+        }
+
+        let mut removed = Vec::new();
+        for (name, hash) in &old.files {
+            if self.files.contains_key(name) {
+                continue;
+            }
+            let path = old.root.join(name);
+            if !path.is_file() || other_paths.contains(&path) {
+                continue;
+            }
+            match file_checker::hash_file(&path) {
+                Ok(current) if &current == hash => removed.push(PathBuf::from(name)),
+                Ok(_) => println!("⚠️ {} 已被上游删除，但本地有修改，保留不动", name),
+                Err(e) => eprintln!("⚠️ 无法读取 {}: {}", name, e),
+            }
+        }
+        removed
+    }
+
+    /// 本清单将覆盖的、安装后被用户修改过的文件（相对路径）
+    ///
+    /// 旧清单中的文件按哈希判断；旧清单中没有但已存在的文件视为用户自己的文件。
+    /// 没有旧清单（或目标目录变化）时无法判断是否修改过，只把与新版本不同的
+    /// 用户自定义文件（如 custom_phrase.txt、*.custom.yaml）视为冲突；
+    /// 内容与新版本相同或属于 other_paths 的文件不算冲突
+    pub fn conflicts_with(&self, old: &Manifest, other_paths: &HashSet<PathBuf>) -> Vec<PathBuf> {
+        let known = !old.files.is_empty() && old.root == self.root;
+
+        let mut conflicts = Vec::new();
+        for (name, hash) in &self.files {
+            if !known && !is_customizable(name) {
+                continue;
+            }
+            let path = self.root.join(name);
+            if !path.is_file() || other_paths.contains(&path) {
+                continue;
+            }
+            let current = match file_checker::hash_file(&path) {
+                Ok(current) => current,
+                Err(e) => {
+                    eprintln!("⚠️ 无法读取 {}: {}", name, e);
+                    continue;
+                }
+            };
+            if &current == hash || old.files.get(name) == Some(&current) {
+                continue;
+            }
+            conflicts.push(PathBuf::from(name));
+        }
+        conflicts
+    }
+}
+
+/// 用户修改过的文件与新版本的冲突
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub component: String,
+    /// 用户的文件
+    pub path: PathBuf,
+    /// keep_user 时为新版本的 `.new` 文件，backup 时为用户文件的备份
+    pub saved_as: PathBuf,
+    pub policy: ConflictPolicy,
+}
+
+/// 按策略处理上游已删除的文件，返回处理的文件数量
+pub fn clean_removed(
+    root: &Path,
+    removed: &[PathBuf],
+    policy: RemovedFilePolicy,
+    quarantine_dir: &Path,
+) -> usize {
+    if removed.is_empty() {
+        return 0;
+    }
+
+    let mut count = 0;
+    for relative in removed {
+        let path = root.join(relative);
+        let result = match policy {
+            RemovedFilePolicy::Keep => {
+                println!("  保留上游已删除的文件: {}", relative.display());
+                continue;
+            }
+            RemovedFilePolicy::Delete => fs::remove_file(&path),
+            RemovedFilePolicy::Quarantine => {
+                let destination = quarantine_dir.join(relative);
+                destination
+                    .parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|_| move_file(&path, &destination))
+            }
+        };
+        match result {
+            Ok(()) => {
+                println!("  🗑️ 移除上游已删除的文件: {}", relative.display());
+                staging::remove_empty_parents(root, &path);
+                count += 1;
+            }
+            Err(e) => eprintln!("  ⚠️ 无法移除 {}: {}", relative.display(), e),
+        }
+    }
+    if policy == RemovedFilePolicy::Quarantine && count > 0 {
+        println!("  已移动到: {:?}", quarantine_dir);
+    }
+    count
+}
+
+/// 用户通常会自行编辑的文件
+fn is_customizable(name: &str) -> bool {
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    file_name.ends_with(".custom.yaml")
+        || (file_name.starts_with("custom_phrase") && file_name.ends_with(".txt"))
+}
+
+/// 清单中使用的相对路径，统一以 `/` 分隔
+fn key(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// 移动文件，跨文件系统时复制后删除
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
@@ -0,0 +1,435 @@
+//! 下载镜像
+//!
+//! 按配置顺序依次尝试各镜像站，全部失败后直接从 GitHub 下载。
+//! 配置为 `auto` 时对候选镜像测速，按测速结果排序，结果缓存一段时间。
+//!
+//! 镜像可以是域名（`gh-proxy.com`，在原始链接前加上镜像地址），
+//! 也可以是含占位符的地址模板，如 `https://kkgithub.com/{path}`，支持的占位符：
+//!
+//! - `{url}`：完整的原始链接
+//! - `{path}`：去掉 `https://github.com/` 后的路径
+//! - `{owner}`、`{repo}`：仓库所有者和名称
+//! - `{tag}`、`{file}`：Release 标签和文件名，只适用于 Release 资产链接
+
+use super::transport::Transport;
+use crate::{run_log::unix_now, types::UpdateConfig};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// 直接从 GitHub 下载时的下载源名称
+pub const DIRECT: &str = "GitHub";
+
+/// 自动选择镜像的配置值
+pub const AUTO: &str = "auto";
+
+/// 测速结果的缓存文件名，位于缓存目录中
+pub const PROBE_CACHE_FILENAME: &str = "mirror_probe.json";
+
+/// 自动模式下除配置的镜像外参与测速的镜像
+const KNOWN_MIRRORS: [&str; 3] = ["gh-proxy.com", "github.sagolu.top", "github.chenc.dev"];
+
+/// 模板中可用的占位符
+const PLACEHOLDERS: [&str; 6] = ["url", "path", "owner", "repo", "tag", "file"];
+
+/// 会经过镜像的链接前缀
+const GITHUB_PREFIX: &str = "https://github.com/";
+
+/// 测速时下载的字节数
+const PROBE_BYTES: u64 = 256 * 1024;
+
+/// 一个下载地址及其来源
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadSource {
+    /// 镜像站名称，直连时为 [`DIRECT`]
+    pub name: String,
+    /// 配置中的镜像项，直连时为 [`DIRECT`]
+    pub mirror: String,
+    pub url: String,
+}
+
+/// 按配置顺序生成各镜像的下载地址，最后是原始地址
+///
+/// 只有 GitHub 的下载链接会经过镜像，模板中的占位符不适用于该链接时跳过该镜像
+pub fn sources(mirrors: &[String], url: &str) -> Vec<DownloadSource> {
+    let mut sources = Vec::new();
+    if url.starts_with(GITHUB_PREFIX) {
+        for mirror in mirrors.iter().map(|m| m.trim()).filter(|m| !m.is_empty()) {
+            if let Some(mirror_url) = mirror_url(mirror, url) {
+                sources.push(DownloadSource {
+                    name: display_name(mirror),
+                    mirror: mirror.to_string(),
+                    url: mirror_url,
+                });
+            }
+        }
+    }
+    sources.push(DownloadSource {
+        name: DIRECT.to_string(),
+        mirror: DIRECT.to_string(),
+        url: url.to_string(),
+    });
+    sources
+}
+
+/// 检查镜像配置项，模板须以 http(s):// 开头、只含已知占位符且包含文件路径
+pub fn validate(mirror: &str) -> Result<(), String> {
+    let mirror = mirror.trim();
+    if mirror.chars().any(char::is_whitespace) {
+        return Err("不能包含空白字符".to_string());
+    }
+    if !is_template(mirror) {
+        return Ok(());
+    }
+    if !mirror.starts_with("http://") && !mirror.starts_with("https://") {
+        return Err("模板须以 http:// 或 https:// 开头".to_string());
+    }
+
+    let mut names = Vec::new();
+    let mut rest = mirror;
+    while let Some(start) = rest.find(['{', '}']) {
+        if rest[start..].starts_with('}') {
+            return Err("多余的 }".to_string());
+        }
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| "缺少 }".to_string())?;
+        let name = &rest[start + 1..start + end];
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(format!("未知的占位符 {{{}}}", name));
+        }
+        names.push(name);
+        rest = &rest[start + end + 1..];
+    }
+    if !names.iter().any(|n| ["url", "path", "file"].contains(n)) {
+        return Err("模板须包含 {url}、{path} 或 {file}".to_string());
+    }
+    Ok(())
+}
+
+/// 镜像的显示名称：域名直接显示，模板显示其中的主机名
+pub fn display_name(mirror: &str) -> String {
+    if !is_template(mirror) {
+        return mirror.to_string();
+    }
+    let without_scheme = mirror.split_once("://").map_or(mirror, |(_, rest)| rest);
+    without_scheme
+        .split('/')
+        .next()
+        .unwrap_or(without_scheme)
+        .to_string()
+}
+
+/// 是否配置为自动选择镜像
+pub fn is_auto(mirrors: &[String]) -> bool {
+    mirrors.iter().any(|m| m.trim().eq_ignore_ascii_case(AUTO))
+}
+
+/// 参与测速的镜像：配置中的镜像在前，其后是内置的镜像，不含 `auto`
+pub fn candidates(mirrors: &[String]) -> Vec<String> {
+    let mut candidates: Vec<String> = Vec::new();
+    let configured = mirrors
+        .iter()
+        .map(|m| m.trim())
+        .filter(|m| !m.is_empty() && !m.eq_ignore_ascii_case(AUTO));
+    for mirror in configured.chain(KNOWN_MIRRORS) {
+        if !candidates.iter().any(|c| c == mirror) {
+            candidates.push(mirror.to_string());
+        }
+    }
+    candidates
+}
+
+/// 测速使用的已知资产：配置中的模型文件
+pub fn probe_url(config: &UpdateConfig) -> String {
+    format!(
+        "https://github.com/{}/releases/download/{}/{}",
+        config.model_repo, config.model_tag, config.model_file_name
+    )
+}
+
+/// 单个下载源的测速结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProbeResult {
+    /// 配置中的镜像项，直连时为 [`DIRECT`]
+    pub name: String,
+    /// 连接耗时（毫秒），请求失败时为 None
+    pub latency_ms: Option<u64>,
+    /// 下载速度（字节/秒）
+    pub throughput: u64,
+}
+
+impl ProbeResult {
+    pub fn is_healthy(&self) -> bool {
+        self.latency_ms.is_some() && self.throughput > 0
+    }
+}
+
+/// 测速结果缓存
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProbeCache {
+    pub probed_at: u64,
+    /// 按速度从快到慢排列，不可用的在最后
+    pub results: Vec<ProbeResult>,
+}
+
+impl ProbeCache {
+    /// 读取未过期的缓存，ttl 为有效时长
+    pub fn load(path: &Path, ttl: Duration) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        let cache: Self = serde_json::from_str(&content).ok()?;
+        let age = unix_now().saturating_sub(cache.probed_at);
+        (age < ttl.as_secs()).then_some(cache)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// 比直连更快的可用镜像，按速度排列；直连不可用时返回全部可用镜像
+    pub fn ranked_mirrors(&self) -> Vec<String> {
+        self.results
+            .iter()
+            .filter(|r| r.is_healthy())
+            .take_while(|r| r.name != DIRECT)
+            .map(|r| r.name.clone())
+            .collect()
+    }
+}
+
+/// 依次对各镜像和直连测速，结果按速度排序
+///
+/// 先请求 1 字节测量连接耗时，再请求 [`PROBE_BYTES`] 字节测量下载速度
+pub fn probe(transport: &dyn Transport, candidates: &[String], url: &str) -> ProbeCache {
+    let mut results: Vec<ProbeResult> = sources(candidates, url)
+        .into_iter()
+        .map(|source| {
+            print!("  测速 {} ... ", source.name);
+            let _ = std::io::Write::flush(&mut std::io::stdout());
+            let result = probe_source(transport, &source);
+            match result.latency_ms {
+                Some(latency) if result.is_healthy() => println!(
+                    "{} ms, {:.1} KB/s",
+                    latency,
+                    result.throughput as f64 / 1024.0
+                ),
+                _ => println!("不可用"),
+            }
+            result
+        })
+        .collect();
+    results.sort_by_key(|r| {
+        (
+            !r.is_healthy(),
+            std::cmp::Reverse(r.throughput),
+            r.latency_ms,
+        )
+    });
+
+    ProbeCache {
+        probed_at: unix_now(),
+        results,
+    }
+}
+
+fn probe_source(transport: &dyn Transport, source: &DownloadSource) -> ProbeResult {
+    let mut result = ProbeResult {
+        name: source.mirror.clone(),
+        latency_ms: None,
+        throughput: 0,
+    };
+
+    // 按实际收到的字节数计算，服务器忽略 Range 时也只读取所需的部分
+    let start = Instant::now();
+    match transport.fetch_prefix(&source.url, &[("Range", "bytes=0-0")], 1) {
+        Ok((status, _)) if (200..300).contains(&status) => {
+            result.latency_ms = Some(start.elapsed().as_millis() as u64);
+        }
+        _ => return result,
+    }
+
+    let range = format!("bytes=0-{}", PROBE_BYTES - 1);
+    let start = Instant::now();
+    if let Ok((status, received)) =
+        transport.fetch_prefix(&source.url, &[("Range", &range)], PROBE_BYTES)
+    {
+        if (200..300).contains(&status) {
+            let elapsed = start.elapsed().as_secs_f64().max(0.001);
+            result.throughput = (received as f64 / elapsed) as u64;
+        }
+    }
+    result
+}
+
+/// 将 GitHub 下载链接转换为镜像站链接，如 `https://gh-proxy.com/https://github.com/...`
+///
+/// 模板中的占位符不适用于该链接时返回 None
+fn mirror_url(mirror: &str, github_url: &str) -> Option<String> {
+    if is_template(mirror) {
+        return expand_template(mirror, github_url);
+    }
+    let mirror = mirror.trim_end_matches('/');
+    if mirror.starts_with("http") {
+        Some(format!("{}/{}", mirror, github_url))
+    } else {
+        Some(format!("https://{}/{}", mirror, github_url))
+    }
+}
+
+fn is_template(mirror: &str) -> bool {
+    mirror.contains(['{', '}'])
+}
+
+/// 用原始链接的各部分替换模板中的占位符
+fn expand_template(template: &str, github_url: &str) -> Option<String> {
+    let path = github_url.strip_prefix(GITHUB_PREFIX)?;
+    let segments: Vec<&str> = path.split('/').collect();
+    let is_asset = segments.len() == 6 && segments[2..4] == ["releases", "download"];
+    let value = |name: &str| match name {
+        "url" => Some(github_url),
+        "path" => Some(path),
+        "owner" => segments.first().copied(),
+        "repo" => segments.get(1).copied(),
+        "tag" if is_asset => Some(segments[4]),
+        "file" if is_asset => Some(segments[5]),
+        _ => None,
+    };
+
+    let mut result = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}')?;
+        result.push_str(&rest[..start]);
+        result.push_str(value(&rest[start + 1..end])?);
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sources() {
+        let url = "https://github.com/amzxyz/rime_wanxiang/releases/download/v1/a.zip";
+        let mirrors = vec![
+            "gh-proxy.com".to_string(),
+            " ".to_string(),
+            "https://ghfast.top/".to_string(),
+        ];
+        let urls: Vec<String> = sources(&mirrors, url).into_iter().map(|s| s.url).collect();
+        assert_eq!(
+            urls,
+            vec![
+                format!("https://gh-proxy.com/{}", url),
+                format!("https://ghfast.top/{}", url),
+                url.to_string(),
+            ]
+        );
+
+        let other = sources(&mirrors, "https://example.com/a.zip");
+        assert_eq!(other.len(), 1);
+        assert_eq!(other[0].name, DIRECT);
+    }
+
+    #[test]
+    fn test_templates() {
+        let url = "https://github.com/amzxyz/rime_wanxiang/releases/download/v1/a.zip";
+        let mirrors = vec![
+            "https://kkgithub.com/{path}".to_string(),
+            "https://cdn.example.com/gh/{owner}/{repo}@{tag}/{file}".to_string(),
+            "https://proxy.example.com/?u={url}".to_string(),
+        ];
+        let sources = sources(&mirrors, url);
+        let names: Vec<&str> = sources.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "kkgithub.com",
+                "cdn.example.com",
+                "proxy.example.com",
+                DIRECT
+            ]
+        );
+        assert_eq!(
+            sources[0].url,
+            "https://kkgithub.com/amzxyz/rime_wanxiang/releases/download/v1/a.zip"
+        );
+        assert_eq!(
+            sources[1].url,
+            "https://cdn.example.com/gh/amzxyz/rime_wanxiang@v1/a.zip"
+        );
+        assert_eq!(
+            sources[2].url,
+            format!("https://proxy.example.com/?u={}", url)
+        );
+        assert_eq!(sources[1].mirror, mirrors[1]);
+
+        // {tag}、{file} 不适用于非 Release 资产链接
+        let archive = "https://github.com/amzxyz/rime_wanxiang/archive/refs/heads/main.zip";
+        assert_eq!(super::sources(&mirrors, archive).len(), 3);
+
+        for mirror in mirrors
+            .iter()
+            .map(String::as_str)
+            .chain(["gh-proxy.com", AUTO])
+        {
+            assert_eq!(validate(mirror), Ok(()), "{}", mirror);
+        }
+        for invalid in [
+            "https://a.com/{owner}/{repo}",
+            "https://a.com/{name}",
+            "https://a.com/{path",
+            "https://a.com/path}",
+            "a.com/{path}",
+            "gh proxy.com",
+        ] {
+            assert!(validate(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_candidates_and_ranking() {
+        let mirrors = vec!["auto".to_string(), "ghfast.top".to_string()];
+        assert!(is_auto(&mirrors));
+        assert!(!is_auto(&["gh-proxy.com".to_string()]));
+        let candidates = candidates(&mirrors);
+        assert_eq!(candidates[0], "ghfast.top");
+        assert_eq!(candidates.len(), 1 + KNOWN_MIRRORS.len());
+
+        let result = |name: &str, latency_ms: Option<u64>, throughput: u64| ProbeResult {
+            name: name.to_string(),
+            latency_ms,
+            throughput,
+        };
+        let mut cache = ProbeCache {
+            probed_at: unix_now(),
+            results: vec![
+                result("a", Some(50), 900),
+                result(DIRECT, Some(300), 500),
+                result("b", Some(80), 100),
+                result("c", None, 0),
+            ],
+        };
+        assert_eq!(cache.ranked_mirrors(), vec!["a".to_string()]);
+        cache.results[1].latency_ms = None;
+        assert_eq!(
+            cache.ranked_mirrors(),
+            vec!["a".to_string(), "b".to_string()]
+        );
+
+        let path = std::env::temp_dir().join(format!("rime_probe_{}.json", std::process::id()));
+        cache.save(&path).unwrap();
+        assert!(ProbeCache::load(&path, Duration::from_secs(3600)).is_some());
+        cache.probed_at -= 7200;
+        cache.save(&path).unwrap();
+        assert!(ProbeCache::load(&path, Duration::from_secs(3600)).is_none());
+        let _ = fs::remove_file(&path);
+    }
+}
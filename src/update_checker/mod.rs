@@ -1,4 +1,19 @@
 pub mod core;
+pub mod custom_deploy;
+pub mod fcitx5_manager;
 pub mod file_operations;
+pub mod frontend;
 pub mod github_client;
+pub mod ibus_manager;
+pub mod install_state;
+pub mod local_transport;
+pub mod manifest;
+pub mod mirror;
+pub mod path_filter;
+pub mod snapshot;
+pub mod staging;
+pub mod transport;
 pub mod weasel_manager;
+
+#[cfg(test)]
+pub mod test_support;
@@ -0,0 +1,113 @@
+//! 按 glob 模式筛选压缩包中要安装的文件
+//!
+//! 模式相对于解压目录，以 `/` 分隔。不含 `/` 的模式匹配任意层级的文件或目录名，
+//! 如 `*.lua`；含 `/` 的模式匹配完整路径，如 `lua/*.lua`、`lua/**`。
+//! 模式匹配某个目录时，目录中的全部文件也视为匹配。
+
+use crate::types::FilterConfig;
+use glob::{MatchOptions, Pattern};
+use std::path::Path;
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl PathFilter {
+    /// 编译配置中的模式，无效的模式给出警告后忽略
+    pub fn new(config: &FilterConfig) -> Self {
+        Self {
+            include: compile(&config.include),
+            exclude: compile(&config.exclude),
+        }
+    }
+
+    /// 是否安装该文件：未配置 include 时默认安装，exclude 优先
+    pub fn allows(&self, relative: &Path) -> bool {
+        let path = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        (self.include.is_empty() || matches_any(&self.include, &path))
+            && !matches_any(&self.exclude, &path)
+    }
+}
+
+/// 检查完整路径、各级父目录和各级名称
+fn matches_any(patterns: &[Pattern], path: &str) -> bool {
+    let prefixes: Vec<&str> = path
+        .match_indices('/')
+        .map(|(i, _)| &path[..i])
+        .chain(std::iter::once(path))
+        .collect();
+
+    patterns.iter().any(|pattern| {
+        if pattern.as_str().contains('/') {
+            prefixes
+                .iter()
+                .any(|p| pattern.matches_with(p, MATCH_OPTIONS))
+        } else {
+            path.split('/')
+                .any(|part| pattern.matches_with(part, MATCH_OPTIONS))
+        }
+    })
+}
+
+fn compile(patterns: &[String]) -> Vec<Pattern> {
+    patterns
+        .iter()
+        .filter_map(|p| match Pattern::new(p) {
+            Ok(pattern) => Some(pattern),
+            Err(e) => {
+                eprintln!("⚠️ 无效的路径模式 {}: {}，已忽略", p, e);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> PathFilter {
+        PathFilter::new(&FilterConfig {
+            include: include.iter().map(|s| s.to_string()).collect(),
+            exclude: exclude.iter().map(|s| s.to_string()).collect(),
+        })
+    }
+
+    #[test]
+    fn test_allows() {
+        let allows = |f: &PathFilter, p: &str| f.allows(Path::new(p));
+
+        let none = filter(&[], &[]);
+        assert!(allows(&none, "default.yaml"));
+
+        let f = filter(&[], &["default.yaml", "weasel.yaml"]);
+        assert!(!allows(&f, "default.yaml"));
+        assert!(allows(&f, "wanxiang.schema.yaml"));
+        assert!(allows(&f, "custom/default.yaml.bak"));
+
+        let f = filter(&["*.yaml", "lua/wanxiang*.lua"], &["lua/wanxiang_test.lua"]);
+        assert!(allows(&f, "dicts/base.dict.yaml"));
+        assert!(allows(&f, "lua/wanxiang_core.lua"));
+        assert!(!allows(&f, "lua/wanxiang_test.lua"));
+        assert!(!allows(&f, "lua/other.lua"));
+        assert!(!allows(&f, "lua/sub/wanxiang.lua"));
+
+        // 匹配目录时排除其中全部文件
+        let f = filter(&[], &["lua", "opencc/**"]);
+        assert!(!allows(&f, "lua/a/b.lua"));
+        assert!(!allows(&f, "opencc/emoji.txt"));
+        assert!(allows(&f, "dicts/lua.dict.yaml"));
+    }
+}
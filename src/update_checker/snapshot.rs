@@ -0,0 +1,228 @@
+//! 更新前快照
+//!
+//! 应用更新前复制将被覆盖的文件，并记录将新增的文件和原先安装的版本，
+//! 用于 `rollback` 命令和部署失败时的自动恢复。
+//!
+//! 目录结构：`<状态目录>/snapshots/<组件>/<快照 ID>/{snapshot.json, files/...}`
+
+use super::{manifest::Manifest, staging};
+use crate::{run_log::unix_now, types::UpdateInfo};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const META_FILENAME: &str = "snapshot.json";
+const FILES_DIR: &str = "files";
+
+/// 快照信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub id: String,
+    pub component: String,
+    pub created_at: u64,
+    /// 本次要应用的版本
+    pub tag: String,
+    /// 文件所在的目标目录
+    pub target: PathBuf,
+    /// 被覆盖的文件（相对于目标目录），已复制到快照中
+    pub replaced: Vec<PathBuf>,
+    /// 新增的文件，恢复时删除
+    pub added: Vec<PathBuf>,
+    /// 更新前已安装的版本
+    pub previous: Option<UpdateInfo>,
+    /// 更新前的文件清单，恢复后写回安装状态
+    #[serde(default)]
+    pub previous_manifest: Manifest,
+}
+
+pub struct SnapshotStore {
+    root: PathBuf,
+    keep: usize,
+}
+
+impl SnapshotStore {
+    /// keep 为每个组件保留的快照数量，0 表示不创建快照
+    pub fn new(root: &Path, keep: usize) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            keep,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.keep > 0
+    }
+
+    /// 复制 target 中将被 files 覆盖的文件，并按保留数量清理旧快照
+    pub fn create(
+        &self,
+        component: &str,
+        tag: &str,
+        target: &Path,
+        files: &[PathBuf],
+        previous: Option<&UpdateInfo>,
+        previous_manifest: &Manifest,
+    ) -> Result<Snapshot, Box<dyn std::error::Error>> {
+        let created_at = unix_now();
+
+        let component_dir = self.root.join(component);
+        let mut id = created_at.to_string();
+        let mut n = 1;
+        while component_dir.join(&id).exists() {
+            n += 1;
+            id = format!("{}-{}", created_at, n);
+        }
+        let dir = component_dir.join(&id);
+        fs::create_dir_all(dir.join(FILES_DIR))?;
+
+        let mut snapshot = Snapshot {
+            id,
+            component: component.to_string(),
+            created_at,
+            tag: tag.to_string(),
+            target: target.to_path_buf(),
+            replaced: Vec::new(),
+            added: Vec::new(),
+            previous: previous.cloned(),
+            previous_manifest: previous_manifest.clone(),
+        };
+
+        let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+            for relative in files {
+                let source = target.join(relative);
+                if source.is_file() {
+                    let copy = dir.join(FILES_DIR).join(relative);
+                    if let Some(parent) = copy.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::copy(&source, &copy)?;
+                    snapshot.replaced.push(relative.clone());
+                } else {
+                    snapshot.added.push(relative.clone());
+                }
+            }
+            fs::write(
+                dir.join(META_FILENAME),
+                serde_json::to_string_pretty(&snapshot)?,
+            )?;
+            Ok(())
+        })();
+        if let Err(e) = result {
+            let _ = staging::remove_path(&dir);
+            return Err(e);
+        }
+
+        println!(
+            "📦 已创建 {} 快照 {} ({} 个文件)",
+            component,
+            snapshot.id,
+            snapshot.replaced.len()
+        );
+        self.prune(component);
+        Ok(snapshot)
+    }
+
+    /// 列出快照，最新的在前
+    pub fn list(&self, component: Option<&str>) -> Vec<Snapshot> {
+        let components: Vec<PathBuf> = match component {
+            Some(component) => vec![self.root.join(component)],
+            None => fs::read_dir(&self.root)
+                .map(|entries| entries.flatten().map(|e| e.path()).collect())
+                .unwrap_or_default(),
+        };
+
+        let mut snapshots: Vec<Snapshot> = components
+            .iter()
+            .filter_map(|dir| fs::read_dir(dir).ok())
+            .flat_map(|entries| entries.flatten())
+            .filter_map(|entry| {
+                let content = fs::read_to_string(entry.path().join(META_FILENAME)).ok()?;
+                serde_json::from_str(&content).ok()
+            })
+            .collect();
+        snapshots.sort_by(|a, b| (b.created_at, &b.id).cmp(&(a.created_at, &a.id)));
+        snapshots
+    }
+
+    /// 查找快照，未指定 ID 时返回最新的一个
+    pub fn find(&self, component: Option<&str>, id: Option<&str>) -> Option<Snapshot> {
+        self.list(component)
+            .into_iter()
+            .find(|s| id.is_none_or(|id| s.id == id))
+    }
+
+    /// 恢复快照中的文件，并删除更新时新增的文件
+    pub fn restore(&self, snapshot: &Snapshot) -> Result<(), Box<dyn std::error::Error>> {
+        let files_dir = self.dir(snapshot).join(FILES_DIR);
+        for relative in &snapshot.replaced {
+            staging::replace_file(&files_dir.join(relative), &snapshot.target.join(relative))?;
+        }
+        for relative in &snapshot.added {
+            let path = snapshot.target.join(relative);
+            staging::remove_path(&path)?;
+            staging::remove_empty_parents(&snapshot.target, &path);
+        }
+        Ok(())
+    }
+
+    pub fn remove(&self, snapshot: &Snapshot) {
+        let _ = staging::remove_path(&self.dir(snapshot));
+    }
+
+    fn dir(&self, snapshot: &Snapshot) -> PathBuf {
+        self.root.join(&snapshot.component).join(&snapshot.id)
+    }
+
+    /// 只保留最新的 keep 个快照
+    fn prune(&self, component: &str) {
+        for snapshot in self.list(Some(component)).iter().skip(self.keep) {
+            println!("🗑️ 清理旧快照: {} {}", component, snapshot.id);
+            self.remove(snapshot);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_restore_and_prune() {
+        let root = std::env::temp_dir().join(format!("rime_snapshot_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let target = root.join("user");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("a.yaml"), "old").unwrap();
+
+        let store = SnapshotStore::new(&root.join("snapshots"), 2);
+        let files = [PathBuf::from("a.yaml"), PathBuf::from("lua/b.lua")];
+        let snapshot = store
+            .create("schema", "v2", &target, &files, None, &Manifest::default())
+            .unwrap();
+        assert_eq!(snapshot.replaced, vec![PathBuf::from("a.yaml")]);
+        assert_eq!(snapshot.added, vec![PathBuf::from("lua/b.lua")]);
+
+        // 模拟更新
+        fs::write(target.join("a.yaml"), "new").unwrap();
+        fs::create_dir_all(target.join("lua")).unwrap();
+        fs::write(target.join("lua/b.lua"), "new").unwrap();
+
+        let found = store.find(Some("schema"), None).unwrap();
+        assert_eq!(found.id, snapshot.id);
+        store.restore(&found).unwrap();
+        assert_eq!(fs::read_to_string(target.join("a.yaml")).unwrap(), "old");
+        assert!(!target.join("lua").exists());
+
+        for _ in 0..3 {
+            store
+                .create("schema", "v3", &target, &files, None, &Manifest::default())
+                .unwrap();
+        }
+        assert_eq!(store.list(Some("schema")).len(), 2);
+        assert!(store.find(None, Some(&snapshot.id)).is_none());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,389 @@
+//! 分阶段应用更新
+//!
+//! 先把新文件准备在目标旁边的临时目录中，校验通过后再逐个重命名到位。
+//! 中途失败时撤销已完成的重命名，保证组件要么全是旧文件，要么全是新文件。
+//!
+//! 替换开始前在目标旁边写入记录要移动文件的日志，全部文件移动完成后删除日志即为完成替换。
+//! 进程在替换中途退出时，下次运行由 [`recover`] 按日志和备份恢复到全是旧文件的状态。
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// 替换日志的后缀，如 `.dicts.swap.json`
+const JOURNAL_SUFFIX: &str = "swap.json";
+
+/// 目标旁边的临时目录或文件路径，如 `dicts` -> `.dicts.staging`
+pub fn sibling_path(target: &Path, suffix: &str) -> PathBuf {
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "target".to_string());
+    target.with_file_name(format!(".{}.{}", name, suffix))
+}
+
+/// 将暂存目录中的文件移动到目标目录，覆盖同名文件，保留目标中其他文件
+///
+/// 任一步骤失败时恢复全部被覆盖的文件并删除新增的文件和目录。
+/// 完成后删除暂存目录，返回移动的文件数量。
+pub fn apply_staged(staging: &Path, target: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+    let files = list_files(staging)?;
+    if files.is_empty() {
+        return Err(format!("暂存目录为空: {:?}", staging).into());
+    }
+
+    let backup = sibling_path(target, "old");
+    let journal = sibling_path(target, JOURNAL_SUFFIX);
+    if backup.exists() || journal.exists() {
+        return Err(format!("{:?} 上次的替换未完成，需要先恢复", target).into());
+    }
+    fs::write(&journal, serde_json::to_string(&files)?)?;
+
+    let mut swap = Swap {
+        target,
+        backup: &backup,
+        moved: Vec::new(),
+        created_dirs: Vec::new(),
+    };
+    for relative in &files {
+        if let Err(e) = swap.move_in(staging, relative) {
+            swap.rollback();
+            let _ = remove_path(&backup);
+            let _ = fs::remove_file(&journal);
+            return Err(format!("替换 {:?} 失败，已恢复原文件: {}", relative, e).into());
+        }
+    }
+
+    // 删除日志即完成替换，之后残留的备份只需清理
+    fs::remove_file(&journal)?;
+    let _ = remove_path(&backup);
+    let _ = remove_path(staging);
+    Ok(files.len())
+}
+
+/// 恢复上次中断的替换，返回是否进行了恢复
+///
+/// 有日志时替换未完成：备份中的文件移回目标目录，日志中记录、已从暂存目录移走且没有备份的文件
+/// 是新增的，予以删除。没有日志时替换已经完成，只是没来得及清理，删除残留的备份
+pub fn recover(target: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+    let backup = sibling_path(target, "old");
+    let staging = sibling_path(target, "staging");
+    let journal = sibling_path(target, JOURNAL_SUFFIX);
+    if !journal.exists() {
+        remove_path(&backup)?;
+        return Ok(false);
+    }
+
+    let backed_up = if backup.is_dir() {
+        list_files(&backup)?
+    } else {
+        Vec::new()
+    };
+    for relative in &backed_up {
+        let destination = target.join(relative);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if fs::symlink_metadata(&destination).is_ok() {
+            fs::remove_file(&destination)?;
+        }
+        fs::rename(backup.join(relative), &destination)?;
+    }
+
+    let files: Vec<PathBuf> = serde_json::from_str(&fs::read_to_string(&journal)?)?;
+    for relative in files.iter().filter(|r| !backed_up.contains(r)) {
+        let destination = target.join(relative);
+        if !staging.join(relative).exists() && destination.is_file() {
+            fs::remove_file(&destination)?;
+            remove_empty_parents(target, &destination);
+        }
+    }
+
+    remove_path(&backup)?;
+    remove_path(&staging)?;
+    remove_path(&journal)?;
+    Ok(true)
+}
+
+/// 用新文件替换目标文件
+///
+/// 先复制到目标旁边，再把旧文件改名让出位置后改名到位，
+/// 这样即使旧文件仍被输入法占用也不会写坏它。
+pub fn replace_file(source: &Path, target: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let new_path = sibling_path(target, "new");
+    let old_path = sibling_path(target, "old");
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::copy(source, &new_path)?;
+
+    let had_old = target.exists();
+    if had_old {
+        let _ = fs::remove_file(&old_path);
+        if let Err(e) = fs::rename(target, &old_path) {
+            let _ = fs::remove_file(&new_path);
+            return Err(e.into());
+        }
+    }
+    if let Err(e) = fs::rename(&new_path, target) {
+        if had_old {
+            let _ = fs::rename(&old_path, target);
+        }
+        let _ = fs::remove_file(&new_path);
+        return Err(e.into());
+    }
+
+    // 旧文件可能仍被占用，删除失败时留到下次
+    if had_old {
+        let _ = fs::remove_file(&old_path);
+    }
+    Ok(())
+}
+
+/// 删除文件或目录，不存在时忽略
+pub fn remove_path(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// 删除 path 所在的空目录，直到 root 为止（不含 root）
+pub fn remove_empty_parents(root: &Path, path: &Path) {
+    let mut parent = path.parent();
+    while let Some(dir) = parent {
+        if dir == root || !dir.starts_with(root) || fs::remove_dir(dir).is_err() {
+            break;
+        }
+        parent = dir.parent();
+    }
+}
+
+/// 递归列出目录中的所有文件（相对路径，按名称排序）
+pub fn list_files(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        for entry in fs::read_dir(root.join(&relative))? {
+            let entry = entry?;
+            let path = relative.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// 记录已完成的替换，用于失败时撤销
+struct Swap<'a> {
+    target: &'a Path,
+    backup: &'a Path,
+    /// (相对路径, 是否覆盖了旧文件)
+    moved: Vec<(PathBuf, bool)>,
+    created_dirs: Vec<PathBuf>,
+}
+
+impl Swap<'_> {
+    fn move_in(&mut self, staging: &Path, relative: &Path) -> io::Result<()> {
+        let destination = self.target.join(relative);
+        if let Some(parent) = destination.parent() {
+            self.create_dirs(parent)?;
+        }
+
+        let had_old = fs::symlink_metadata(&destination).is_ok();
+        if had_old {
+            let backup_path = self.backup.join(relative);
+            if let Some(parent) = backup_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(&destination, &backup_path)?;
+        }
+        if let Err(e) = fs::rename(staging.join(relative), &destination) {
+            if had_old {
+                let _ = fs::rename(self.backup.join(relative), &destination);
+            }
+            return Err(e);
+        }
+        self.moved.push((relative.to_path_buf(), had_old));
+        Ok(())
+    }
+
+    /// 创建缺少的目录，并记录下来以便撤销
+    fn create_dirs(&mut self, dir: &Path) -> io::Result<()> {
+        let mut missing = Vec::new();
+        let mut current = Some(dir);
+        while let Some(path) = current {
+            if path.exists() {
+                break;
+            }
+            missing.push(path.to_path_buf());
+            current = path.parent();
+        }
+        for path in missing.into_iter().rev() {
+            fs::create_dir(&path)?;
+            self.created_dirs.push(path);
+        }
+        Ok(())
+    }
+
+    fn rollback(&mut self) {
+        for (relative, had_old) in self.moved.drain(..).rev() {
+            let destination = self.target.join(&relative);
+            let _ = fs::remove_file(&destination);
+            if had_old {
+                let _ = fs::rename(self.backup.join(&relative), &destination);
+            }
+        }
+        for dir in self.created_dirs.drain(..).rev() {
+            let _ = fs::remove_dir(dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("rime_staging_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_apply_staged() {
+        let root = temp_dir("apply");
+        let target = root.join("user");
+        let staging = sibling_path(&target, "staging");
+        fs::create_dir_all(target.join("lua")).unwrap();
+        fs::write(target.join("a.yaml"), "old").unwrap();
+        fs::write(target.join("lua/custom.lua"), "mine").unwrap();
+        fs::create_dir_all(staging.join("lua")).unwrap();
+        fs::write(staging.join("a.yaml"), "new").unwrap();
+        fs::write(staging.join("lua/b.lua"), "new").unwrap();
+
+        assert_eq!(apply_staged(&staging, &target).unwrap(), 2);
+        assert_eq!(fs::read_to_string(target.join("a.yaml")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(target.join("lua/b.lua")).unwrap(), "new");
+        assert_eq!(
+            fs::read_to_string(target.join("lua/custom.lua")).unwrap(),
+            "mine"
+        );
+        assert!(!staging.exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_apply_staged_rollback() {
+        let root = temp_dir("rollback");
+        let target = root.join("user");
+        let staging = sibling_path(&target, "staging");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("a.yaml"), "old").unwrap();
+        // 目标中同名的普通文件导致无法创建目录 x/
+        fs::write(target.join("x"), "file").unwrap();
+        fs::create_dir_all(staging.join("x")).unwrap();
+        fs::write(staging.join("a.yaml"), "new").unwrap();
+        fs::write(staging.join("b.yaml"), "new").unwrap();
+        fs::write(staging.join("x/y.yaml"), "new").unwrap();
+
+        assert!(apply_staged(&staging, &target).is_err());
+        assert_eq!(fs::read_to_string(target.join("a.yaml")).unwrap(), "old");
+        assert!(!target.join("b.yaml").exists());
+        assert_eq!(fs::read_to_string(target.join("x")).unwrap(), "file");
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_recover_interrupted_swap() {
+        for moved in 0..=3 {
+            let root = temp_dir(&format!("recover_{}", moved));
+            let target = root.join("user");
+            let staging = sibling_path(&target, "staging");
+            fs::create_dir_all(&target).unwrap();
+            fs::write(target.join("a.yaml"), "old a").unwrap();
+            fs::write(target.join("b.yaml"), "old b").unwrap();
+            fs::write(target.join("custom.yaml"), "mine").unwrap();
+            fs::create_dir_all(staging.join("lua")).unwrap();
+            fs::write(staging.join("a.yaml"), "new a").unwrap();
+            fs::write(staging.join("b.yaml"), "new b").unwrap();
+            fs::write(staging.join("lua/c.lua"), "new c").unwrap();
+
+            // 按 apply_staged 的步骤替换前 moved 个文件后中断，不执行撤销
+            let files = list_files(&staging).unwrap();
+            let backup = sibling_path(&target, "old");
+            fs::write(
+                sibling_path(&target, JOURNAL_SUFFIX),
+                serde_json::to_string(&files).unwrap(),
+            )
+            .unwrap();
+            let mut swap = Swap {
+                target: &target,
+                backup: &backup,
+                moved: Vec::new(),
+                created_dirs: Vec::new(),
+            };
+            for relative in files.iter().take(moved) {
+                swap.move_in(&staging, relative).unwrap();
+            }
+
+            assert!(apply_staged(&staging, &target).is_err());
+            assert!(recover(&target).unwrap());
+            assert_eq!(fs::read_to_string(target.join("a.yaml")).unwrap(), "old a");
+            assert_eq!(fs::read_to_string(target.join("b.yaml")).unwrap(), "old b");
+            assert_eq!(
+                fs::read_to_string(target.join("custom.yaml")).unwrap(),
+                "mine"
+            );
+            assert!(!target.join("lua").exists(), "moved = {}", moved);
+            assert!(!backup.exists());
+            assert!(!staging.exists());
+            assert!(!recover(&target).unwrap());
+
+            let _ = fs::remove_dir_all(&root);
+        }
+
+        // 没有日志说明替换已完成，只清理残留的备份
+        let root = temp_dir("recover_done");
+        let target = root.join("user");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("a.yaml"), "new").unwrap();
+        let backup = sibling_path(&target, "old");
+        fs::create_dir_all(&backup).unwrap();
+        fs::write(backup.join("a.yaml"), "old").unwrap();
+        assert!(!recover(&target).unwrap());
+        assert_eq!(fs::read_to_string(target.join("a.yaml")).unwrap(), "new");
+        assert!(!backup.exists());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_replace_file() {
+        let root = temp_dir("replace");
+        let source = root.join("download.gram");
+        let target = root.join("user/model.gram");
+        fs::write(&source, "v2").unwrap();
+
+        replace_file(&source, &target).unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "v2");
+        fs::write(&source, "v3").unwrap();
+        replace_file(&source, &target).unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "v3");
+        assert!(!sibling_path(&target, "new").exists());
+        assert!(!sibling_path(&target, "old").exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
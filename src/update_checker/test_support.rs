@@ -0,0 +1,163 @@
+//! 测试用的本地 Release 数据和目录布局
+
+use std::{fs, io::Write, path::PathBuf, rc::Rc};
+
+use serde_json::{json, Value};
+
+use super::{
+    core::UpdateChecker, frontend::FrontendKind, local_transport::LocalTransport,
+    transport::Transport,
+};
+use crate::types::{UpdateConfig, UserPath};
+
+/// 临时目录中的一套完整测试环境
+///
+/// - `releases/`：供 [`LocalTransport`] 读取的 Release 数据
+/// - `user/`：Rime 用户目录
+/// - `cache/`、`state/`：缓存和状态目录
+pub struct Fixture {
+    pub root: PathBuf,
+    pub paths: UserPath,
+    pub config: UpdateConfig,
+}
+
+impl Fixture {
+    pub fn new(name: &str) -> Self {
+        let root =
+            std::env::temp_dir().join(format!("rime_updater_test_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("releases")).unwrap();
+        fs::create_dir_all(root.join("user")).unwrap();
+
+        let paths = UserPath {
+            user: root.join("user"),
+            frontend: FrontendKind::Fcitx5,
+            frontend_root: root.clone(),
+            shared: None,
+            config: root.join("updater_conf.ini"),
+            config_created: false,
+            curl: root.join("curl"),
+            cache: root.join("cache"),
+            state: root.join("state"),
+            logs: root.join("logs"),
+        };
+
+        Self {
+            root,
+            paths,
+            config: UpdateConfig::default(),
+        }
+    }
+
+    pub fn releases_dir(&self) -> PathBuf {
+        self.root.join("releases")
+    }
+
+    /// 使用本地目录传输创建更新检查器
+    pub fn checker(&self) -> UpdateChecker {
+        self.checker_with(Rc::new(LocalTransport::new(&self.releases_dir())))
+    }
+
+    pub fn checker_with(&self, transport: Rc<dyn Transport>) -> UpdateChecker {
+        UpdateChecker::with_transport(&self.paths, self.config.clone(), transport)
+    }
+
+    /// 写入资产文件，返回其下载地址
+    pub fn add_asset(&self, repo: &str, tag: &str, name: &str, data: &[u8]) -> String {
+        let url = format!(
+            "https://github.com/{}/releases/download/{}/{}",
+            repo, tag, name
+        );
+        let path = self.releases_dir().join(format!(
+            "github.com/{}/releases/download/{}/{}",
+            repo, tag, name
+        ));
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+        url
+    }
+
+    /// 写入仓库的 Release 列表（/releases）
+    pub fn set_releases(&self, repo: &str, releases: Value) {
+        self.write_api(&format!("repos/{}/releases", repo), &releases);
+    }
+
+    /// 写入单个 Release（/releases/tags/<tag>）
+    pub fn set_release_by_tag(&self, repo: &str, tag: &str, release: Value) {
+        self.write_api(&format!("repos/{}/releases/tags/{}", repo, tag), &release);
+    }
+
+    /// 写入最新 Release（/releases/latest）
+    pub fn set_latest_release(&self, repo: &str, release: Value) {
+        self.write_api(&format!("repos/{}/releases/latest", repo), &release);
+    }
+
+    fn write_api(&self, path: &str, value: &Value) {
+        let dir = self.releases_dir().join("api.github.com").join(path);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("index.json"), value.to_string()).unwrap();
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// 构造 Release JSON，assets 为 (文件名, 数据, 下载地址, sha3-256)
+///
+/// 资产 ID 由文件名和内容生成，内容变化即视为重新上传
+pub fn release_json(tag: &str, assets: &[(&str, &[u8], &str, Option<&str>)]) -> Value {
+    json!({
+        "tag_name": tag,
+        "published_at": "2026-01-01T00:00:00Z",
+        "body": format!("release {}", tag),
+        "assets": assets
+            .iter()
+            .map(|(name, data, url, sha3)| json!({
+                "id": asset_id(name, data),
+                "name": name,
+                "size": data.len(),
+                "updated_at": "2026-01-01T00:00:00Z",
+                "browser_download_url": url,
+                "sha3-256": sha3,
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// FNV-1a 哈希，作为稳定的伪造资产 ID
+fn asset_id(name: &str, data: &[u8]) -> u32 {
+    name.bytes()
+        .chain(data.iter().copied())
+        .fold(0x811c9dc5u32, |hash, b| {
+            (hash ^ b as u32).wrapping_mul(0x01000193)
+        })
+}
+
+/// 生成包含 `wanxiang.schema.yaml` 和 `dicts/base.dict.yaml` 的压缩包
+///
+/// 文件内容由 seed 决定，不压缩以保证大小超过下载检查的下限
+pub fn fake_zip(seed: u8) -> Vec<u8> {
+    fake_zip_with(&["wanxiang.schema.yaml", "dicts/base.dict.yaml"], seed)
+}
+
+/// 生成包含指定文件的压缩包
+pub fn fake_zip_with(names: &[&str], seed: u8) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options =
+        zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for &name in names {
+        writer.start_file(name, options).unwrap();
+        writer.write_all(&fake_content(name, seed)).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+/// fake_zip 中文件的内容
+pub fn fake_content(name: &str, seed: u8) -> Vec<u8> {
+    let mut data = format!("# {} v{}\n", name, seed).into_bytes();
+    data.extend(std::iter::repeat_n(b'a' + seed % 26, 1024));
+    data
+}
@@ -0,0 +1,672 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+const USER_AGENT: &str = "rime_wanxiang_updater";
+
+/// HTTP 响应
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// 按名称（不区分大小写）查找响应头
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// 下载结果
+#[derive(Debug, Clone)]
+pub struct DownloadResult {
+    pub status: u16,
+    /// 本次响应的长度，断点续传时为剩余部分的长度
+    pub content_length: Option<u64>,
+    pub content_type: Option<String>,
+    pub etag: Option<String>,
+    pub bytes_written: u64,
+}
+
+impl DownloadResult {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// 网络传输抽象
+///
+/// 返回 Err 表示连接、TLS 等传输层错误；HTTP 错误状态码通过 status 字段体现
+pub trait Transport {
+    /// 传输方式名称
+    fn name(&self) -> &'static str;
+
+    /// 发送 GET 请求并读取完整响应
+    fn get(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<HttpResponse, Box<dyn std::error::Error>>;
+
+    /// 下载文件到指定路径
+    ///
+    /// 请求头含 `Range` 且服务器返回 206 时追加到已有文件末尾，否则覆盖
+    fn download(
+        &self,
+        url: &str,
+        save_path: &Path,
+        headers: &[(&str, &str)],
+    ) -> Result<DownloadResult, Box<dyn std::error::Error>>;
+
+    /// 读取并丢弃响应体的前 limit 字节，返回状态码和实际收到的字节数，用于测速
+    ///
+    /// 服务器忽略 `Range` 时也不会读取超过 limit 的内容
+    fn fetch_prefix(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        limit: u64,
+    ) -> Result<(u16, u64), Box<dyn std::error::Error>>;
+}
+
+/// 传输方式配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportKind {
+    /// 优先使用内置 HTTP 客户端，失败时回退到 curl
+    Auto,
+    Native,
+    Curl,
+}
+
+impl TransportKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "auto" => Some(TransportKind::Auto),
+            "native" => Some(TransportKind::Native),
+            "curl" => Some(TransportKind::Curl),
+            _ => None,
+        }
+    }
+}
+
+/// 根据配置创建传输实例
+pub fn create(kind: TransportKind, curl_path: &Path) -> Box<dyn Transport> {
+    let curl = || {
+        if curl_path.exists() {
+            Some(CurlTransport::new(curl_path))
+        } else {
+            None
+        }
+    };
+
+    match kind {
+        TransportKind::Native => Box::new(NativeTransport::new()),
+        TransportKind::Curl => match curl() {
+            Some(curl) => Box::new(curl),
+            None => {
+                eprintln!("⚠️ 未找到 curl: {:?}，改用内置 HTTP 客户端", curl_path);
+                Box::new(NativeTransport::new())
+            }
+        },
+        TransportKind::Auto => Box::new(FallbackTransport {
+            primary: NativeTransport::new(),
+            fallback: curl(),
+        }),
+    }
+}
+
+// ===== 内置 HTTP 客户端 =====
+
+/// 基于 ureq 的内置 HTTP 客户端（rustls）
+pub struct NativeTransport {
+    agent: ureq::Agent,
+}
+
+impl NativeTransport {
+    pub fn new() -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new()
+                .user_agent(USER_AGENT)
+                .timeout_connect(Duration::from_secs(30))
+                .timeout_read(Duration::from_secs(60))
+                .redirects(10)
+                .build(),
+        }
+    }
+
+    /// 发送请求，HTTP 错误状态码同样作为响应返回
+    fn call(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<ureq::Response, Box<dyn std::error::Error>> {
+        let mut request = self.agent.get(url);
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+        match request.call() {
+            Ok(response) => Ok(response),
+            Err(ureq::Error::Status(_, response)) => Ok(response),
+            Err(e) => Err(Box::new(e)),
+        }
+    }
+}
+
+impl Default for NativeTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transport for NativeTransport {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+
+    fn get(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+        let response = self.call(url, headers)?;
+        let status = response.status();
+        let headers = response
+            .headers_names()
+            .into_iter()
+            .filter_map(|name| {
+                let value = response.header(&name)?.to_string();
+                Some((name, value))
+            })
+            .collect();
+        let body = response.into_string()?;
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    fn download(
+        &self,
+        url: &str,
+        save_path: &Path,
+        headers: &[(&str, &str)],
+    ) -> Result<DownloadResult, Box<dyn std::error::Error>> {
+        let response = self.call(url, headers)?;
+        let status = response.status();
+        let content_length = response
+            .header("Content-Length")
+            .and_then(|v| v.parse::<u64>().ok());
+        let content_type = response.header("Content-Type").map(|v| v.to_string());
+        let etag = response.header("ETag").map(|v| v.to_string());
+
+        if !(200..300).contains(&status) {
+            return Ok(DownloadResult {
+                status,
+                content_length,
+                content_type,
+                etag,
+                bytes_written: 0,
+            });
+        }
+
+        let mut file = if status == 206 {
+            OpenOptions::new().append(true).open(save_path)?
+        } else {
+            File::create(save_path)?
+        };
+        let bytes_written =
+            copy_with_progress(&mut response.into_reader(), &mut file, content_length)?;
+        file.flush()?;
+
+        Ok(DownloadResult {
+            status,
+            content_length,
+            content_type,
+            etag,
+            bytes_written,
+        })
+    }
+
+    fn fetch_prefix(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        limit: u64,
+    ) -> Result<(u16, u64), Box<dyn std::error::Error>> {
+        let response = self.call(url, headers)?;
+        let status = response.status();
+        let received = io::copy(&mut response.into_reader().take(limit), &mut io::sink())?;
+        Ok((status, received))
+    }
+}
+
+/// 流式写入文件并显示进度
+fn copy_with_progress(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    total: Option<u64>,
+) -> io::Result<u64> {
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut written = 0u64;
+    let mut last_percent = None;
+
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..n])?;
+        written += n as u64;
+
+        match total {
+            Some(total) if total > 0 => {
+                let percent = written * 100 / total;
+                if last_percent != Some(percent) {
+                    last_percent = Some(percent);
+                    print!(
+                        "\r下载进度: {:>3}% ({:.1} / {:.1} MB)",
+                        percent,
+                        written as f64 / 1048576.0,
+                        total as f64 / 1048576.0
+                    );
+                    let _ = io::stdout().flush();
+                }
+            }
+            _ => {
+                print!("\r已下载: {:.1} MB", written as f64 / 1048576.0);
+                let _ = io::stdout().flush();
+            }
+        }
+    }
+    println!();
+    Ok(written)
+}
+
+// ===== curl =====
+
+/// 调用 curl 可执行文件
+pub struct CurlTransport {
+    curl_path: PathBuf,
+}
+
+impl CurlTransport {
+    pub fn new(curl_path: &Path) -> Self {
+        Self {
+            curl_path: curl_path.to_path_buf(),
+        }
+    }
+
+    fn command(&self, headers: &[(&str, &str)]) -> Command {
+        let mut command = Command::new(&self.curl_path);
+        command.args([
+            "-L", // 跟随重定向
+            "--connect-timeout",
+            "30", // 连接超时
+            "-H",
+            &format!("User-Agent: {}", USER_AGENT),
+        ]);
+        for (name, value) in headers {
+            command.arg("-H").arg(format!("{}: {}", name, value));
+        }
+        command
+    }
+}
+
+impl Transport for CurlTransport {
+    fn name(&self) -> &'static str {
+        "curl"
+    }
+
+    fn get(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+        let output = self
+            .command(headers)
+            .args(["-s", "-w", "\n%{http_code}"])
+            .arg(url)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(format!(
+                "curl 请求失败 ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            )
+            .into());
+        }
+
+        // 最后一行为 -w 输出的状态码；测速时响应体可能是二进制内容
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let (body, status) = stdout.rsplit_once('\n').unwrap_or(("", &stdout));
+        Ok(HttpResponse {
+            status: status.trim().parse()?,
+            headers: Vec::new(),
+            body: body.to_string(),
+        })
+    }
+
+    fn download(
+        &self,
+        url: &str,
+        save_path: &Path,
+        headers: &[(&str, &str)],
+    ) -> Result<DownloadResult, Box<dyn std::error::Error>> {
+        // 先写到单独的文件，确认状态码后再追加或替换，避免错误页面覆盖原文件
+        let resuming = headers.iter().any(|(k, _)| k.eq_ignore_ascii_case("Range"));
+        let output_path = save_path.with_extension("chunk");
+        let header_path = save_path.with_extension("headers");
+
+        // 进度条输出到 stderr，状态码输出到 stdout
+        let output = self
+            .command(headers)
+            .args([
+                "--progress-bar", // 显示进度条
+                "--max-time",
+                "1800", // 最大下载时间(30分钟)
+                "-w",
+                "%{http_code}\n%{content_type}",
+                "-D", // 响应头
+            ])
+            .arg(&header_path)
+            .arg("-o") // 输出文件
+            .arg(&output_path)
+            .arg(url)
+            .stderr(Stdio::inherit())
+            .output();
+        let response_headers = std::fs::read_to_string(&header_path).unwrap_or_default();
+        let _ = std::fs::remove_file(&header_path);
+        let output = match output {
+            Ok(output) if output.status.success() => output,
+            Ok(output) => {
+                let _ = std::fs::remove_file(&output_path);
+                return Err(format!("curl 下载失败，退出码: {}", output.status).into());
+            }
+            Err(e) => {
+                let _ = std::fs::remove_file(&output_path);
+                return Err(e.into());
+            }
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut lines = stdout.lines();
+        let status = lines.next().unwrap_or_default().trim().parse()?;
+        let content_type = lines
+            .next()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+        let (content_length, etag) = parse_response_headers(&response_headers);
+        let mut bytes_written = std::fs::metadata(&output_path)
+            .map(|m| m.len())
+            .unwrap_or(0);
+
+        let result = if status == 206 && resuming {
+            File::open(&output_path).and_then(|mut chunk| {
+                let mut file = OpenOptions::new().append(true).open(save_path)?;
+                io::copy(&mut chunk, &mut file).map(|_| ())
+            })
+        } else if (200..300).contains(&status) {
+            // 没有响应体时 curl 不会创建输出文件
+            if output_path.exists() {
+                std::fs::rename(&output_path, save_path)
+            } else {
+                File::create(save_path).map(|_| ())
+            }
+        } else {
+            bytes_written = 0;
+            Ok(())
+        };
+        let _ = std::fs::remove_file(&output_path);
+        result?;
+
+        Ok(DownloadResult {
+            status,
+            content_length,
+            content_type,
+            etag,
+            bytes_written,
+        })
+    }
+
+    fn fetch_prefix(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        limit: u64,
+    ) -> Result<(u16, u64), Box<dyn std::error::Error>> {
+        // 响应头和响应体都输出到 stdout，读够 limit 字节后结束 curl
+        let mut child = self
+            .command(headers)
+            .args(["-s", "-D", "-", "-o", "-"])
+            .arg(url)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let result = match child.stdout.take() {
+            Some(stdout) => read_prefix(BufReader::new(stdout), limit),
+            None => Err("无法读取 curl 输出".into()),
+        };
+        let _ = child.kill();
+        let _ = child.wait();
+        result
+    }
+}
+
+/// 跳过 curl -D - 输出的各段响应头（含重定向），读取最终响应体的前 limit 字节
+fn read_prefix<R: BufRead>(
+    mut reader: R,
+    limit: u64,
+) -> Result<(u16, u64), Box<dyn std::error::Error>> {
+    loop {
+        let mut status = None;
+        let mut redirected = false;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Err("curl 请求失败".into());
+            }
+            let line = line.trim();
+            if line.is_empty() {
+                if status.is_some() {
+                    break;
+                }
+                continue;
+            }
+            match status {
+                None => {
+                    status = line
+                        .split_whitespace()
+                        .nth(1)
+                        .and_then(|s| s.parse::<u16>().ok());
+                    if status.is_none() {
+                        return Err(format!("无法解析响应: {}", line).into());
+                    }
+                }
+                Some(_) => {
+                    if let Some((name, _)) = line.split_once(':') {
+                        redirected |= name.eq_ignore_ascii_case("Location");
+                    }
+                }
+            }
+        }
+        let status = status.unwrap_or_default();
+        if (100..200).contains(&status) || ((300..400).contains(&status) && redirected) {
+            continue;
+        }
+        let received = io::copy(&mut reader.take(limit), &mut io::sink())?;
+        return Ok((status, received));
+    }
+}
+
+/// 从 curl -D 输出中取最后一个响应的 Content-Length 和 ETag
+///
+/// 跟随重定向时会包含多段响应头，只有最后一段对应实际内容
+fn parse_response_headers(text: &str) -> (Option<u64>, Option<String>) {
+    let last = text
+        .split("\r\n\r\n")
+        .flat_map(|block| block.split("\n\n"))
+        .filter(|block| !block.trim().is_empty())
+        .last()
+        .unwrap_or_default();
+
+    let mut content_length = None;
+    let mut etag = None;
+    for line in last.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("Content-Length") {
+            content_length = value.parse().ok();
+        } else if name.eq_ignore_ascii_case("ETag") {
+            etag = Some(value.to_string());
+        }
+    }
+    (content_length, etag)
+}
+
+// ===== 自动回退 =====
+
+/// 优先使用内置客户端，传输层出错时回退到 curl
+pub struct FallbackTransport {
+    primary: NativeTransport,
+    fallback: Option<CurlTransport>,
+}
+
+impl Transport for FallbackTransport {
+    fn name(&self) -> &'static str {
+        "auto"
+    }
+
+    fn get(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+        match self.primary.get(url, headers) {
+            Ok(response) => Ok(response),
+            Err(e) => match &self.fallback {
+                Some(curl) => {
+                    eprintln!("⚠️ 内置 HTTP 客户端请求失败: {}，改用 curl", e);
+                    curl.get(url, headers)
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    fn download(
+        &self,
+        url: &str,
+        save_path: &Path,
+        headers: &[(&str, &str)],
+    ) -> Result<DownloadResult, Box<dyn std::error::Error>> {
+        match self.primary.download(url, save_path, headers) {
+            Ok(result) => Ok(result),
+            Err(e) => match &self.fallback {
+                Some(curl) => {
+                    eprintln!("⚠️ 内置 HTTP 客户端下载失败: {}，改用 curl", e);
+                    let headers = resume_headers(headers, save_path);
+                    let headers: Vec<(&str, &str)> = headers
+                        .iter()
+                        .map(|(k, v)| (k.as_str(), v.as_str()))
+                        .collect();
+                    curl.download(url, save_path, &headers)
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    fn fetch_prefix(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        limit: u64,
+    ) -> Result<(u16, u64), Box<dyn std::error::Error>> {
+        match self.primary.fetch_prefix(url, headers, limit) {
+            Ok(result) => Ok(result),
+            Err(e) => match &self.fallback {
+                Some(curl) => curl.fetch_prefix(url, headers, limit),
+                None => Err(e),
+            },
+        }
+    }
+}
+
+/// 续传请求失败后重新计算 Range
+///
+/// 失败前可能已经向文件追加了部分内容，按文件当前长度继续，避免重复追加
+fn resume_headers(headers: &[(&str, &str)], save_path: &Path) -> Vec<(String, String)> {
+    let len = std::fs::metadata(save_path).map(|m| m.len()).unwrap_or(0);
+    headers
+        .iter()
+        .map(|(k, v)| {
+            let value = if k.eq_ignore_ascii_case("Range") {
+                format!("bytes={}-", len)
+            } else {
+                v.to_string()
+            };
+            (k.to_string(), value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_headers() {
+        let path = std::env::temp_dir().join(format!("rime_resume_{}.part", std::process::id()));
+        std::fs::write(&path, vec![0u8; 1500]).unwrap();
+        let headers = resume_headers(&[("Cookie", "a=b"), ("Range", "bytes=1000-")], &path);
+        assert_eq!(
+            headers,
+            vec![
+                ("Cookie".to_string(), "a=b".to_string()),
+                ("Range".to_string(), "bytes=1500-".to_string()),
+            ]
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_parse_response_headers() {
+        let text = "HTTP/1.1 302 Found\r\nLocation: https://example.com/a.zip\r\nContent-Length: 0\r\n\r\n\
+                    HTTP/2 206\r\ncontent-length: 1024\r\netag: \"abc\"\r\n\r\n";
+        assert_eq!(
+            parse_response_headers(text),
+            (Some(1024), Some("\"abc\"".to_string()))
+        );
+        assert_eq!(parse_response_headers(""), (None, None));
+    }
+
+    #[test]
+    fn test_read_prefix() {
+        // 跟随重定向后服务器忽略 Range 返回完整内容
+        let mut output = b"HTTP/1.1 302 Found\r\nLocation: /a.zip\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 5000\r\n\r\n".to_vec();
+        output.extend(vec![0xffu8; 5000]);
+        assert_eq!(read_prefix(&output[..], 1024).unwrap(), (200, 1024));
+
+        let output = b"HTTP/2 206\r\n\r\nabc";
+        assert_eq!(read_prefix(&output[..], 1024).unwrap(), (206, 3));
+        assert!(read_prefix(&b""[..], 1024).is_err());
+    }
+}
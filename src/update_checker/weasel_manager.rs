@@ -1,20 +1,81 @@
 use std::{
-    os::windows::process::CommandExt, path::PathBuf, process::Command, thread, time::Duration,
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::Duration,
 };
 
+use super::frontend::Frontend;
+use crate::platform;
+
+const WEASEL_MACHINE_KEY: &str = "HKEY_LOCAL_MACHINE\\SOFTWARE\\WOW6432Node\\Rime\\Weasel";
+const WEASEL_USER_KEY: &str = "HKEY_CURRENT_USER\\Software\\Rime\\Weasel";
+
 pub struct WeaselManager {
     weasel_root: PathBuf,
 }
 
 impl WeaselManager {
-    pub fn new(weasel_path: &PathBuf) -> Self {
+    pub fn new(weasel_path: &Path) -> Self {
         Self {
-            weasel_root: weasel_path.clone(),
+            weasel_root: weasel_path.to_path_buf(),
+        }
+    }
+
+    /// 通过注册表定位小狼毫安装目录
+    pub fn locate() -> Result<Self, Box<dyn std::error::Error>> {
+        let exe = platform::read_registry_value(WEASEL_MACHINE_KEY, "WeaselRoot")
+            .map_err(|e| format!("Failed to get Weasel directory: {}", e))?;
+        Ok(Self::new(&PathBuf::from(exe)))
+    }
+
+    /// 停止小狼毫服务
+    fn stop_weasel_service(&self) {
+        println!("正在停止小狼毫服务...");
+
+        let server_path = self.weasel_root.join("WeaselServer.exe");
+        if server_path.exists() {
+            let _ = Command::new(&server_path).arg("/q").status();
         }
+
+        // 强制结束进程
+        platform::kill_process("WeaselServer.exe");
+        platform::kill_process("WeaselDeployer.exe");
+    }
+
+    /// 启动小狼毫服务
+    fn start_weasel_service(&self) {
+        println!("正在启动小狼毫服务...");
+
+        let server_path = self.weasel_root.join("WeaselServer.exe");
+        if server_path.exists() {
+            let _ = Command::new(&server_path).spawn();
+        }
+    }
+}
+
+impl Frontend for WeaselManager {
+    fn name(&self) -> &'static str {
+        "小狼毫"
+    }
+
+    fn root_dir(&self) -> PathBuf {
+        self.weasel_root.clone()
+    }
+
+    /// 通过注册表读取用户目录
+    fn user_dir(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let user = platform::read_registry_value(WEASEL_USER_KEY, "RimeUserDir")
+            .map_err(|e| format!("Failed to get Rime user directory: {}", e))?;
+        Ok(PathBuf::from(user))
+    }
+
+    fn shared_dir(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        Ok(self.weasel_root.join("data"))
     }
 
     /// 部署小狼毫
-    pub fn deploy(&self) -> bool {
+    fn deploy(&self) -> bool {
         println!("正在部署小狼毫...");
 
         let deployer_path = self.weasel_root.join("WeaselDeployer.exe");
@@ -24,7 +85,7 @@ impl WeaselManager {
         }
 
         // 首先停止服务
-        self.stop_weasel_service();
+        self.stop();
 
         // 等待一段时间确保服务完全停止
         thread::sleep(Duration::from_secs(2));
@@ -39,7 +100,7 @@ impl WeaselManager {
 
                     // 等待部署完成后重启服务
                     thread::sleep(Duration::from_secs(1));
-                    self.start_weasel_service();
+                    self.start();
 
                     true
                 } else {
@@ -54,34 +115,16 @@ impl WeaselManager {
         }
     }
 
-    /// 停止小狼毫服务
-    fn stop_weasel_service(&self) {
-        println!("正在停止小狼毫服务...");
-
-        let server_path = self.weasel_root.join("WeaselServer.exe");
-        if server_path.exists() {
-            let _ = Command::new(&server_path).arg("/q").status();
-        }
-
-        // 使用taskkill强制结束进程
-        let _ = Command::new("taskkill")
-            .args(&["/f", "/im", "WeaselServer.exe"])
-            .creation_flags(0x08000000)
-            .output();
-
-        let _ = Command::new("taskkill")
-            .args(&["/f", "/im", "WeaselDeployer.exe"])
-            .creation_flags(0x08000000)
-            .output();
+    fn stop(&self) {
+        self.stop_weasel_service();
     }
 
-    /// 启动小狼毫服务
-    fn start_weasel_service(&self) {
-        println!("正在启动小狼毫服务...");
+    fn start(&self) {
+        self.start_weasel_service();
+    }
 
-        let server_path = self.weasel_root.join("WeaselServer.exe");
-        if server_path.exists() {
-            let _ = Command::new(&server_path).spawn();
-        }
+    /// 小狼毫安装目录中自带 curl.exe 等工具
+    fn bundled_tool(&self, name: &str) -> PathBuf {
+        self.weasel_root.join(format!("{}.exe", name))
     }
 }
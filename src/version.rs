@@ -0,0 +1,289 @@
+use std::{cmp::Ordering, fmt};
+
+/// 版本号，兼容语义化版本
+///
+/// 支持 `v` 前缀、任意数量的数字段（缺少的段视为 0）、
+/// `-beta.1` 形式的预发布标识和 `+build` 形式的构建元数据。
+/// 构建元数据不参与比较。
+#[derive(Debug, Clone)]
+pub struct Version {
+    parts: Vec<u64>,
+    pre: Vec<Identifier>,
+    build: Option<String>,
+}
+
+/// 预发布标识中的一段
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Identifier {
+    Numeric(u64),
+    Alpha(String),
+}
+
+impl Version {
+    /// 解析版本号，非版本格式的标签（如 `dict-nightly`）返回 None
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let text = text
+            .strip_prefix('v')
+            .or_else(|| text.strip_prefix('V'))
+            .unwrap_or(text);
+
+        let (text, build) = match text.split_once('+') {
+            Some((text, build)) if is_valid_identifiers(build) => (text, Some(build.to_string())),
+            Some(_) => return None,
+            None => (text, None),
+        };
+        let (core, pre) = match text.split_once('-') {
+            Some((core, pre)) if is_valid_identifiers(pre) => {
+                (core, pre.split('.').map(Identifier::parse).collect())
+            }
+            Some(_) => return None,
+            None => (text, Vec::new()),
+        };
+
+        let parts = core
+            .split('.')
+            .map(|part| {
+                if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                    None
+                } else {
+                    part.parse::<u64>().ok()
+                }
+            })
+            .collect::<Option<Vec<u64>>>()?;
+
+        Some(Self { parts, pre, build })
+    }
+
+    /// 是否为预发布版本，如 `1.0.0-beta.1`
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+}
+
+impl Identifier {
+    fn parse(text: &str) -> Self {
+        match text.parse::<u64>() {
+            Ok(n) if text.bytes().all(|b| b.is_ascii_digit()) => Identifier::Numeric(n),
+            _ => Identifier::Alpha(text.to_string()),
+        }
+    }
+}
+
+/// 以 `.` 分隔的非空标识，仅允许字母、数字和 `-`
+fn is_valid_identifiers(text: &str) -> bool {
+    text.split('.').all(|part| {
+        !part.is_empty() && part.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+    })
+}
+
+impl Ord for Identifier {
+    /// 数字标识小于字母标识，同类按数值或字典序比较
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Identifier::Numeric(a), Identifier::Numeric(b)) => a.cmp(b),
+            (Identifier::Numeric(_), Identifier::Alpha(_)) => Ordering::Less,
+            (Identifier::Alpha(_), Identifier::Numeric(_)) => Ordering::Greater,
+            (Identifier::Alpha(a), Identifier::Alpha(b)) => a.cmp(b),
+        }
+    }
+}
+
+impl PartialOrd for Identifier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.parts.len().max(other.parts.len());
+        let part = |v: &Version, i: usize| v.parts.get(i).copied().unwrap_or(0);
+        (0..len)
+            .map(|i| part(self, i).cmp(&part(other, i)))
+            .find(|o| o.is_ne())
+            .unwrap_or_else(|| {
+                // 正式版本高于同号的预发布版本
+                match (self.pre.is_empty(), other.pre.is_empty()) {
+                    (true, true) => Ordering::Equal,
+                    (true, false) => Ordering::Greater,
+                    (false, true) => Ordering::Less,
+                    (false, false) => self.pre.cmp(&other.pre),
+                }
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self.parts.iter().map(|p| p.to_string()).collect();
+        write!(f, "{}", parts.join("."))?;
+        if !self.pre.is_empty() {
+            let pre: Vec<String> = self
+                .pre
+                .iter()
+                .map(|id| match id {
+                    Identifier::Numeric(n) => n.to_string(),
+                    Identifier::Alpha(s) => s.clone(),
+                })
+                .collect();
+            write!(f, "-{}", pre.join("."))?;
+        }
+        if let Some(build) = &self.build {
+            write!(f, "+{}", build)?;
+        }
+        Ok(())
+    }
+}
+
+/// 判断远程标签是否比本地标签新
+///
+/// 两者都是版本号时按版本比较；否则只要标签不同就视为有更新
+pub fn is_newer_tag(remote: &str, local: &str) -> bool {
+    match (Version::parse(remote), Version::parse(local)) {
+        (Some(remote), Some(local)) => remote > local,
+        _ => remote.trim() != local.trim(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn v(text: &str) -> Version {
+        Version::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_order() {
+        assert!(v("2.0.0") > v("1.0.5"));
+        assert!(v("10.2.3") > v("v10.2"));
+        assert_eq!(v("v1.2"), v("1.2.0"));
+        assert!(v("1.0.0-beta") < v("1.0.0"));
+        assert!(v("1.0.0-alpha") < v("1.0.0-alpha.1"));
+        assert!(v("1.0.0-alpha.beta") < v("1.0.0-beta.2"));
+        assert!(v("1.0.0-beta.2") < v("1.0.0-beta.11"));
+        assert!(v("1.0.0-rc.1") < v("1.0.0"));
+        assert_eq!(v("1.0.0+build.5"), v("1.0.0+build.7"));
+        assert_eq!(v("V3.1.4-rc.1+abc").to_string(), "3.1.4-rc.1+abc");
+
+        assert!(v("v10.2.3-beta").is_prerelease());
+        assert!(!v("v10.2.3+build").is_prerelease());
+
+        assert!(Version::parse("dict-nightly").is_none());
+        assert!(Version::parse("LTS").is_none());
+        assert!(Version::parse("1..2").is_none());
+        assert!(Version::parse("").is_none());
+        assert!(Version::parse("1.0.0-").is_none());
+    }
+
+    #[test]
+    fn test_is_newer_tag() {
+        assert!(is_newer_tag("v1.0.1", "1.0.0"));
+        assert!(!is_newer_tag("1.0.5", "2.0.0"));
+        assert!(!is_newer_tag("v2.0", "2.0.0"));
+        assert!(is_newer_tag("nightly-2", "nightly-1"));
+        assert!(!is_newer_tag("LTS", "LTS"));
+    }
+
+    fn version_strategy() -> impl Strategy<Value = String> {
+        (
+            prop::collection::vec(0u64..1000, 1..5),
+            prop::option::of(prop::collection::vec("[a-z]{1,5}|[0-9]{1,3}", 1..3)),
+            prop::option::of("[a-z0-9]{1,6}"),
+        )
+            .prop_map(|(parts, pre, build)| {
+                let mut text = parts
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<_>>()
+                    .join(".");
+                if let Some(pre) = pre {
+                    text.push('-');
+                    text.push_str(&pre.join("."));
+                }
+                if let Some(build) = build {
+                    text.push('+');
+                    text.push_str(&build);
+                }
+                text
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_parse_never_panics(text in "\\PC*") {
+            let _ = Version::parse(&text);
+            let _ = is_newer_tag(&text, "1.0.0");
+        }
+
+        #[test]
+        fn prop_display_roundtrip(text in version_strategy()) {
+            let version = v(&text);
+            prop_assert_eq!(v(&version.to_string()), version.clone());
+            prop_assert_eq!(v(&format!("v{}", text)), version);
+        }
+
+        #[test]
+        fn prop_order_is_total(a in version_strategy(), b in version_strategy()) {
+            let (a, b) = (v(&a), v(&b));
+            prop_assert_eq!(a.cmp(&b), b.cmp(&a).reverse());
+            prop_assert_eq!(a == b, a.cmp(&b) == Ordering::Equal);
+        }
+
+        #[test]
+        fn prop_order_is_transitive(
+            a in version_strategy(),
+            b in version_strategy(),
+            c in version_strategy(),
+        ) {
+            let mut versions = [v(&a), v(&b), v(&c)];
+            versions.sort();
+            prop_assert!(versions[0] <= versions[2]);
+        }
+
+        #[test]
+        fn prop_bump_is_newer(
+            parts in prop::collection::vec(0u64..1000, 1..5),
+            index in 0usize..5,
+        ) {
+            let index = index % parts.len();
+            let mut bumped = parts.clone();
+            bumped[index] += 1;
+            for part in bumped.iter_mut().skip(index + 1) {
+                *part = 0;
+            }
+            let join = |p: &[u64]| p.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(".");
+            prop_assert!(is_newer_tag(&join(&bumped), &join(&parts)));
+            prop_assert!(!is_newer_tag(&join(&parts), &join(&bumped)));
+        }
+
+        #[test]
+        fn prop_trailing_zeros_equal(parts in prop::collection::vec(0u64..1000, 1..4)) {
+            let text = parts.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(".");
+            prop_assert_eq!(v(&text), v(&format!("{}.0", text)));
+        }
+
+        #[test]
+        fn prop_prerelease_precedes_release(parts in prop::collection::vec(0u64..1000, 1..4), pre in "[a-z]{1,5}") {
+            let text = parts.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(".");
+            let prerelease = v(&format!("{}-{}", text, pre));
+            prop_assert!(prerelease < v(&text));
+        }
+    }
+}